@@ -2,28 +2,173 @@ use cirrus_theming::v1::{Colour, Theme};
 use eframe::egui::{self, Align, Color32, Context, CursorIcon, Frame, Layout, Margin, Rect, RichText, Slider, Stroke, Vec2};
 use egui_notify::ToastLevel;
 use strum::IntoEnumIterator;
-use std::time::Duration;
+use std::{mem, path::PathBuf, time::Duration};
 
-use crate::{config::config::Config, files, notifier::NotifierAPI, upscale::{OutputExt, Upscale}, windows::about::AboutWindow, Image};
+use crate::{batch::Batch, config::config::Config, files, image_folder::{self, FolderFilter}, model_files, notifier::NotifierAPI, upscale::{format_duration, JobState, OutputExt, Upscale}, windows::{about::AboutWindow, models::ModelsWindow, setup::SetupScreen, watch::WatchWindow}, Error, Image};
 
 pub struct Aeternum<'a> {
     theme: Theme,
     image: Option<Image>,
+    // Images opened together, `image` is the one of them being previewed.
+    batch: Option<Batch>,
+    folder_filter: FolderFilter,
     about_box: AboutWindow<'a>,
+    models_window: ModelsWindow,
+    watch_window: WatchWindow,
     notifier: NotifierAPI,
-    upscale: Upscale
+    upscale: Upscale,
+    setup: Option<SetupScreen>
 }
 
 impl<'a> Aeternum<'a> {
-    pub fn new(image: Option<Image>, theme: Theme, mut notifier: NotifierAPI, upscale: Upscale, config: Config) -> Self {
+    pub fn new(
+        image: Option<Image>,
+        theme: Theme,
+        mut notifier: NotifierAPI,
+        upscale: Upscale,
+        setup: Option<SetupScreen>,
+        config: Config
+    ) -> Self {
         let about_box = AboutWindow::new(&config, &mut notifier);
+        let models_window = ModelsWindow::new(&config, &mut notifier);
+        let watch_window = WatchWindow::new(&config);
+
+        let folder_filter = match FolderFilter::new(&config.upscale.folder_include, &config.upscale.folder_exclude) {
+            Ok(folder_filter) => folder_filter,
+            Err(error) => {
+                notifier.toasts.lock().unwrap().toast_and_log(
+                    format!("{} Every image in opened folders is upscaled instead.", error).into(),
+                    ToastLevel::Error
+                );
+
+                FolderFilter::default()
+            }
+        };
 
         Self {
             image,
+            batch: None,
+            folder_filter,
             theme,
             notifier,
             about_box,
-            upscale
+            models_window,
+            watch_window,
+            upscale,
+            setup
+        }
+    }
+
+    // Opens what was dropped or picked. A single image is opened on its own, several images or
+    // a folder become a batch. Once an image is open anything else is added to its batch.
+    fn open_paths(&mut self, paths: Vec<PathBuf>) {
+        if paths.is_empty() {
+            return;
+        }
+
+        if self.image.is_none() && paths.len() == 1 && !paths[0].is_dir() {
+            match Image::from_path(paths[0].clone()) {
+                Ok(image) => self.image = Some(image),
+                Err(error) => {
+                    self.notifier.toasts.lock().unwrap().toast_and_log(
+                        error.into(), ToastLevel::Error
+                    );
+                }
+            }
+
+            return;
+        }
+
+        let folder = Some(paths[0].clone()).filter(|path| paths.len() == 1 && path.is_dir());
+        let mut images = Vec::new();
+        let mut errors = Vec::new();
+
+        for path in paths {
+            let opened = match path.is_dir() {
+                true => image_folder::scan(&path, &self.folder_filter, self.upscale.options.output.as_slice())
+                    .map(|scan| {
+                        errors.extend(scan.warnings);
+                        scan.images
+                    }),
+                false => Image::from_path(path).map(|image| vec![image])
+            };
+
+            match opened {
+                Ok(opened) => images.extend(opened),
+                Err(error) => errors.push(error)
+            }
+        }
+
+        // Files that couldn't be opened are only a warning as long as the others could.
+        let level = match images.is_empty() {
+            true => ToastLevel::Error,
+            false => ToastLevel::Warning
+        };
+
+        for error in errors {
+            self.notifier.toasts.lock().unwrap()
+                .toast_and_log(error.into(), level.clone())
+                .duration(Some(Duration::from_secs(10)));
+        }
+
+        if images.is_empty() {
+            return;
+        }
+
+        let batch = match (self.batch.as_mut(), self.image.take()) {
+            (Some(batch), _) => batch,
+            (None, image) => self.batch.insert(
+                Batch::new(image.into_iter().chain(images.drain(..)).collect(), folder)
+            )
+        };
+
+        batch.add(images);
+        self.image = batch.selected_image().cloned();
+    }
+
+    // Swaps the shared options with the previewed batch image's own ones, so the options panel
+    // edits those. Returns false if it has none, calling it again swaps them back.
+    fn swap_batch_options(&mut self) -> bool {
+        match self.batch.as_mut().and_then(Batch::selected_overrides_mut) {
+            Some(overrides) => {
+                mem::swap(&mut self.upscale.options, overrides);
+                true
+            },
+            None => false
+        }
+    }
+
+    // Picks up models added to or removed from the watched models folders, and the checksums
+    // of the models worked out in the background.
+    fn reload_models(&mut self) {
+        for error in self.upscale.apply_verified() {
+            self.notifier.toasts.lock().unwrap()
+                .toast_and_log(error.into(), ToastLevel::Warning)
+                .duration(Some(Duration::from_secs(10)));
+        }
+
+        let changes = match self.upscale.reload_changed() {
+            Some(Ok(changes)) => changes,
+            Some(Err(error)) => {
+                self.notifier.toasts.lock().unwrap()
+                    .toast_and_log(error.into(), ToastLevel::Error)
+                    .duration(Some(Duration::from_secs(10)));
+
+                return;
+            },
+            None => return
+        };
+
+        let mut toasts = self.notifier.toasts.lock().unwrap();
+
+        if let Some(message) = changes.message() {
+            toasts.toast_and_log(message.into(), ToastLevel::Info)
+                .duration(Some(Duration::from_secs(5)));
+        }
+
+        for warning in changes.warnings {
+            toasts.toast_and_log(warning.into(), ToastLevel::Warning)
+                .duration(Some(Duration::from_secs(10)));
         }
     }
 
@@ -48,7 +193,7 @@ impl<'a> Aeternum<'a> {
 impl eframe::App for Aeternum<'_> {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
         self.about_box.handle_input(ctx);
-        self.upscale.update();
+        self.models_window.handle_input(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             let window_rect = ctx.input(|i: &egui::InputState| i.screen_rect());
@@ -56,29 +201,46 @@ impl eframe::App for Aeternum<'_> {
             self.notifier.update(ctx);
             self.about_box.update(ctx);
 
-            if self.image.is_none() {
-                // Collect dropped files.
-                ctx.input(|i| {
-                    let dropped_files = &i.raw.dropped_files;
-
-                    if !dropped_files.is_empty() {
-                        let path = dropped_files.first().unwrap()
-                            .path
-                            .as_ref()
-                            .unwrap();
-
-                        match Image::from_path(path.clone()) {
-                            Ok(image) => self.image = Some(image),
-                            Err(error) => {
-                                self.notifier.toasts.lock().unwrap().toast_and_log(
-                                    error.into(), ToastLevel::Error
-                                );
-                                return;
-                            }
-                        };
-                    }
-                });
+            if let Some(setup) = self.setup.as_mut() {
+                if setup.show(ui, &mut self.notifier, &mut self.upscale) {
+                    self.setup = None;
+                }
+
+                return;
+            }
+
+            // Started here rather than in `new` since the setup screen may hand over a new `Upscale`.
+            let repaint_ctx = ctx.clone();
+            let watch = self.upscale.watch(move |delay| repaint_ctx.request_repaint_after(delay));
+
+            if let Err(error) = watch {
+                self.notifier.toasts.lock().unwrap()
+                    .toast_and_log(error.into(), ToastLevel::Warning)
+                    .duration(Some(Duration::from_secs(10)));
+            }
 
+            let verified_ctx = ctx.clone();
+            self.upscale.verify_in_background(move || verified_ctx.request_repaint());
+
+            self.reload_models();
+
+            self.models_window.update(ctx, &mut self.upscale, &mut self.notifier);
+            self.watch_window.update(ctx, &mut self.upscale, &mut self.notifier);
+
+            // Model archives can be dropped at any time, anything else dropped is taken as an image.
+            let (archives, dropped_files): (Vec<PathBuf>, Vec<PathBuf>) = ctx.input(|i| {
+                i.raw.dropped_files.iter()
+                    .filter_map(|file| file.path.clone())
+                    .partition(|path| model_files::is_archive(path))
+            });
+
+            for archive in archives {
+                self.models_window.install_archive(archive, &mut self.upscale, &mut self.notifier);
+            }
+
+            self.open_paths(dropped_files);
+
+            if self.image.is_none() {
                 ui.centered_and_justified(|ui| {
                     let image_width: f32 = 145.0;
                     let file_is_hovering = !ctx.input(|i| i.raw.hovered_files.is_empty());
@@ -143,15 +305,17 @@ impl eframe::App for Aeternum<'_> {
                 return;
             }
 
-            let image = self.image.as_ref().unwrap();
             let side_panel_size = 240.0;
+            let own_options = self.swap_batch_options();
+            let mut start_batch = false;
+            let image = self.image.clone().unwrap();
 
             egui::SidePanel::left("options_panel")
                 .show_separator_line(true)
                 .exact_width(side_panel_size)
                 .resizable(false)
                 .show(ctx, |ui| {
-                    ui.add_enabled_ui(!self.upscale.upscaling, |ui| {
+                    ui.scope(|ui| {
                         egui::Grid::new("options_grid")
                             .spacing([20.0, 45.0])
                             .show(ui, |ui| {
@@ -159,7 +323,7 @@ impl eframe::App for Aeternum<'_> {
                                     ui.label("Model");
 
                                     let selected = match &self.upscale.options.model {
-                                        Some(model) => model.name.clone(),
+                                        Some(model) => model.display_name().to_string(),
                                         None => "Select a Model".to_string(),
                                     };
 
@@ -169,21 +333,63 @@ impl eframe::App for Aeternum<'_> {
                                             .width(230.0)
                                             .show_ui(ui, |ui| {
                                                 for model in self.upscale.models.iter() {
-                                                    ui.selectable_value(
+                                                    let integrity = model.check_integrity();
+
+                                                    let label = match integrity {
+                                                        Ok(_) => RichText::new(model.display_name()),
+                                                        Err(_) => RichText::new(format!("⚠ {}", model.display_name()))
+                                                            .color(ui.visuals().warn_fg_color)
+                                                    };
+
+                                                    let response = ui.selectable_value(
                                                         &mut self.upscale.options.model,
                                                         Some(model.clone()),
-                                                        model.name.to_string()
+                                                        label
                                                     );
+
+                                                    // Picking a model starts off at the scale it was made for.
+                                                    if let Some(native_scale) = model.native_scale.filter(|_| response.changed()) {
+                                                        self.upscale.options.scale = native_scale as i32;
+                                                    }
+
+                                                    if model.info.is_some() || integrity.is_err() {
+                                                        response.on_hover_ui(|ui| {
+                                                            if let Err(error) = &integrity {
+                                                                ui.label(RichText::new(error.message()).color(ui.visuals().warn_fg_color));
+                                                            }
+
+                                                            if let Some(info) = &model.info {
+                                                                ui.label(RichText::new(&model.name).monospace());
+                                                                info.show(ui);
+                                                            }
+                                                        });
+                                                    }
                                                 }
                                             });
                                     });
+
+                                    if let Some(info) = self.upscale.options.model.as_ref().and_then(|model| model.info.as_ref()) {
+                                        Frame::group(ui.style()).show(ui, |ui| {
+                                            info.show(ui);
+                                        });
+                                    }
                                 });
                                 ui.end_row();
 
+                                let capabilities = match &self.upscale.options.model {
+                                    Some(model) => self.upscale.capabilities(model),
+                                    None => Default::default()
+                                };
+
+                                let scales = match capabilities.scales {
+                                    Some(scales) => scales.iter().min().copied().unwrap_or(1)..=scales.iter().max().copied().unwrap_or(16),
+                                    None => 1..=16
+                                };
+
                                 ui.vertical_centered_justified(|ui| {
                                     ui.label("Scale");
                                     ui.add(
-                                        Slider::new(&mut self.upscale.options.scale, 1..=16)
+                                        Slider::new(&mut self.upscale.options.scale, scales)
                                     );
 
                                     let scale = self.upscale.options.scale;
@@ -191,6 +397,21 @@ impl eframe::App for Aeternum<'_> {
                                     let height = image.image_size.height as i32;
 
                                     ui.label(format!("({}x{})", width * scale, height * scale));
+
+                                    let native_scale = self.upscale.options.model.as_ref()
+                                        .and_then(|model| model.native_scale)
+                                        .map(|native_scale| native_scale as i32);
+
+                                    if let Some(native_scale) = native_scale.filter(|native_scale| scale % native_scale != 0) {
+                                        ui.label(
+                                            RichText::new(format!("This model is {}x, other scales are resampled and look softer.", native_scale))
+                                                .color(ui.visuals().warn_fg_color)
+                                        );
+
+                                        if ui.small_button(format!("Use {}x", native_scale)).clicked() {
+                                            self.upscale.options.scale = native_scale;
+                                        }
+                                    }
                                 });
                                 ui.end_row();
 
@@ -202,6 +423,73 @@ impl eframe::App for Aeternum<'_> {
                                 });
                                 ui.end_row();
 
+                                if capabilities.noise {
+                                    ui.vertical_centered_justified(|ui| {
+                                        ui.label("Denoise level");
+                                        ui.add(
+                                            Slider::new(&mut self.upscale.options.noise, -1..=3)
+                                        );
+                                    });
+                                    ui.end_row();
+                                }
+
+                                if capabilities.syncgap {
+                                    ui.vertical_centered_justified(|ui| {
+                                        ui.label("Sync gap mode");
+                                        ui.add(
+                                            Slider::new(&mut self.upscale.options.syncgap, 0..=3)
+                                        );
+                                    });
+                                    ui.end_row();
+                                }
+
+                                if capabilities.tuning {
+                                    ui.vertical_centered_justified(|ui| {
+                                        egui::CollapsingHeader::new("Advanced")
+                                            .id_salt("advanced_options")
+                                            .show(ui, |ui| {
+                                                let tuning = &mut self.upscale.options.tuning;
+
+                                                ui.horizontal(|ui| {
+                                                    ui.label("Tile size");
+                                                    ui.add(egui::DragValue::new(&mut tuning.tile_size).range(0..=4096).speed(8.0))
+                                                        .on_hover_text("0 picks a tile size automatically. Lower it if you run out of GPU memory.");
+                                                });
+
+                                                ui.horizontal(|ui| {
+                                                    ui.label("Threads");
+
+                                                    for (threads, hover) in tuning.threads.iter_mut().zip(["Load", "Process", "Save"]) {
+                                                        ui.add(egui::DragValue::new(threads).range(1..=64))
+                                                            .on_hover_text(hover);
+                                                    }
+                                                });
+
+                                                ui.checkbox(&mut tuning.tta, "TTA mode")
+                                                    .on_hover_text("Test-time augmentation, a lot slower but slightly better quality.");
+
+                                                ui.horizontal(|ui| {
+                                                    let mut automatic = tuning.gpu_id.is_none();
+
+                                                    ui.label("GPU");
+
+                                                    if ui.checkbox(&mut automatic, "Auto").changed() {
+                                                        tuning.gpu_id = match automatic {
+                                                            true => None,
+                                                            false => Some(0)
+                                                        };
+                                                    }
+
+                                                    if let Some(gpu_id) = tuning.gpu_id.as_mut() {
+                                                        ui.add(egui::DragValue::new(gpu_id).range(-1..=16))
+                                                            .on_hover_text("-1 upscales on the CPU.");
+                                                    }
+                                                });
+                                            });
+                                    });
+                                    ui.end_row();
+                                }
+
 
                                 ui.vertical_centered_justified(|ui| {
                                     ui.label("Save image as");
@@ -257,14 +545,29 @@ impl eframe::App for Aeternum<'_> {
                                 };
 
                                 ui.vertical_centered_justified(|ui| {
+                                    if own_options {
+                                        ui.small(
+                                            RichText::new("These options are only used for the previewed image.")
+                                                .color(ui.visuals().warn_fg_color)
+                                        );
+                                    }
+
+                                    let label = match &self.batch {
+                                        Some(batch) => format!("Upscale {}", batch.items.len()),
+                                        None => "Upscale".to_string()
+                                    };
+
                                     let upscale_button = ui.add_enabled(
                                         button_enabled,
-                                        egui::Button::new(RichText::new("Upscale").size(20.0))
+                                        egui::Button::new(RichText::new(label).size(20.0))
                                             .min_size([50.0, 60.0].into())
                                     ).on_disabled_hover_text(disabled_text);
 
                                     if upscale_button.clicked() {
-                                        self.upscale.upscale(image.clone(), &mut self.notifier);
+                                        match self.batch.is_some() {
+                                            true => start_batch = true,
+                                            false => self.upscale.upscale(image.clone(), &mut self.notifier)
+                                        }
                                     }
                                 });
                             });
@@ -272,8 +575,158 @@ impl eframe::App for Aeternum<'_> {
                 
                 });
 
+            if own_options {
+                self.swap_batch_options();
+            }
+
+            if start_batch {
+                if let Some(batch) = &self.batch {
+                    let jobs = batch.jobs(&self.upscale.options);
+
+                    if let Err(error) = self.upscale.upscale_each(jobs, &mut self.notifier) {
+                        self.notifier.toasts.lock().unwrap()
+                            .toast_and_log(error.into(), ToastLevel::Error)
+                            .duration(Some(Duration::from_secs(10)));
+                    }
+                }
+            }
+
+            if !self.upscale.queue.is_empty() {
+                egui::SidePanel::right("queue_panel")
+                    .show_separator_line(true)
+                    .exact_width(side_panel_size)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        let jobs = self.upscale.queue.jobs();
+                        let done = jobs.iter().filter(|job| job.state.is_done()).count();
+
+                        ui.vertical_centered_justified(|ui| {
+                            ui.label("Queue");
+                            ui.add(
+                                egui::ProgressBar::new(self.upscale.queue.progress())
+                                    .text(format!("{}/{}", done, jobs.len()))
+                            );
+                        });
+
+                        ui.add_space(5.0);
+
+                        ui.horizontal(|ui| {
+                            let add_button = ui.add_enabled(
+                                self.upscale.options.model.is_some(),
+                                egui::Button::new("Add images")
+                            ).on_disabled_hover_text("Select a model before adding images.");
+
+                            if add_button.clicked() {
+                                match files::select_images() {
+                                    Ok(images) => self.upscale.upscale_many(images, &mut self.notifier),
+                                    Err(error) => {
+                                        self.notifier.toasts.lock().unwrap()
+                                            .toast_and_log(error.into(), ToastLevel::Error)
+                                            .duration(Some(Duration::from_secs(5)));
+                                    }
+                                }
+                            }
+
+                            let folder_button = ui.add_enabled(
+                                self.upscale.options.model.is_some(),
+                                egui::Button::new("Add folder")
+                            ).on_disabled_hover_text("Select a model before adding images.");
+
+                            if folder_button.clicked() {
+                                let scan = files::select_image_folder().and_then(|folder| {
+                                    image_folder::scan(&folder, &self.folder_filter, self.upscale.options.output.as_slice())
+                                });
+
+                                match scan {
+                                    Ok(scan) => {
+                                        for warning in scan.warnings {
+                                            self.notifier.toasts.lock().unwrap()
+                                                .toast_and_log(warning.into(), ToastLevel::Warning)
+                                                .duration(Some(Duration::from_secs(10)));
+                                        }
+
+                                        self.upscale.upscale_many(scan.images, &mut self.notifier);
+                                    },
+                                    Err(Error::NoFileSelected(_)) => {},
+                                    Err(error) => {
+                                        self.notifier.toasts.lock().unwrap()
+                                            .toast_and_log(error.into(), ToastLevel::Error)
+                                            .duration(Some(Duration::from_secs(5)));
+                                    }
+                                }
+                            }
+
+                            if ui.button("Clear finished").clicked() {
+                                self.upscale.queue.clear_finished();
+                            }
+                        });
+
+                        ui.separator();
+
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            for job in jobs.iter() {
+                                ui.group(|ui| {
+                                    ui.set_width(ui.available_width());
+
+                                    ui.label(job.image.path.file_name().unwrap().to_string_lossy());
+
+                                    if let Some(model) = &job.options.model {
+                                        ui.small(format!("{} x{}", model.name, job.options.scale));
+                                    }
+
+                                    if !job.history.is_empty() {
+                                        ui.small(format!("Retried {} time(s)", job.history.len()))
+                                            .on_hover_text(job.history.join("\n"));
+                                    }
+
+                                    ui.horizontal(|ui| {
+                                        match &job.state {
+                                            JobState::Running { progress, .. } => {
+                                                ui.add(
+                                                    egui::ProgressBar::new(*progress)
+                                                        .desired_width(150.0)
+                                                        .show_percentage()
+                                                );
+                                            },
+                                            JobState::Failed(error) => {
+                                                ui.label(job.state.to_string()).on_hover_text(error.message());
+                                            },
+                                            state => {
+                                                ui.label(state.to_string());
+                                            }
+                                        }
+
+                                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                            let action = match job.state {
+                                                JobState::Queued => Some("Remove"),
+                                                _ if job.state.is_active() => Some("Cancel"),
+                                                _ => None
+                                            };
+
+                                            if let Some(action) = action {
+                                                if ui.small_button(action).clicked() {
+                                                    match job.state {
+                                                        JobState::Queued => self.upscale.queue.remove(job.id),
+                                                        _ => self.upscale.queue.cancel(job.id)
+                                                    }
+                                                }
+                                            }
+                                        });
+                                    });
+                                });
+                            }
+                        });
+                    });
+            }
+
             egui::CentralPanel::default()
                 .show(ctx, |ui| {
+                    if let Some(batch) = self.batch.as_mut() {
+                        egui::TopBottomPanel::bottom("batch_strip")
+                            .show_separator_line(false)
+                            .show_inside(ui, |ui| batch.show_strip(ui, &self.upscale.options));
+                    }
+
                     let image_path = format!("file://{}", image.path.to_string_lossy());
 
                     ui.centered_and_justified(|ui| {
@@ -288,6 +741,15 @@ impl eframe::App for Aeternum<'_> {
                     });
                 });
 
+            // Follows the thumbnail picked in the strip, the batch is closed once it's emptied.
+            if let Some(batch) = &self.batch {
+                self.image = batch.selected_image().cloned();
+
+                if self.image.is_none() {
+                    self.batch = None;
+                }
+            }
+
             ctx.request_repaint_after_secs(1.0);
         });
 
@@ -300,6 +762,41 @@ impl eframe::App for Aeternum<'_> {
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if self.setup.is_none() && ui.button("Models").clicked() {
+                            self.models_window.show = !self.models_window.show;
+                        }
+
+                        if self.setup.is_none() {
+                            let watch_text = match self.watch_window.is_watching() {
+                                true => "👁 Watching",
+                                false => "Watch"
+                            };
+
+                            let watch_button = ui.button(watch_text)
+                                .on_hover_text("Upscale new images in a folder automatically.");
+
+                            if watch_button.clicked() {
+                                self.watch_window.show = !self.watch_window.show;
+                            }
+                        }
+
+                        if self.setup.is_none() && self.image.is_none() {
+                            let open_button = ui.button("Open folder")
+                                .on_hover_text("Upscale every image in a folder and its subfolders.");
+
+                            if open_button.clicked() {
+                                match files::select_image_folder() {
+                                    Ok(folder) => self.open_paths(vec![folder]),
+                                    Err(Error::NoFileSelected(_)) => {},
+                                    Err(error) => {
+                                        self.notifier.toasts.lock().unwrap()
+                                            .toast_and_log(error.into(), ToastLevel::Error)
+                                            .duration(Some(Duration::from_secs(5)));
+                                    }
+                                }
+                            }
+                        }
+
                         if self.image.is_some() {
                             let exit_button =
                                 ui.add(
@@ -309,6 +806,10 @@ impl eframe::App for Aeternum<'_> {
                             if exit_button.clicked() {
                                 self.upscale.reset_options();
                                 self.image = None;
+
+                                if let Some(batch) = self.batch.take() {
+                                    batch.forget_images(ctx);
+                                }
                             }
                         }
                     });
@@ -321,7 +822,46 @@ impl eframe::App for Aeternum<'_> {
                 Frame::none()
                     .outer_margin(Margin {right: 12.0, bottom: 8.0, ..Default::default()})
             ).show(ctx, |ui| {
-                if let Ok(loading_status) = self.notifier.loading_status.try_read() {
+                let running_job = self.upscale.queue.jobs().into_iter()
+                    .find(|job| job.state.is_active());
+
+                if let Some(job) = running_job {
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if ui.button("Cancel").clicked() {
+                            self.upscale.queue.cancel_running();
+                        }
+
+                        let image_name = job.image.path.file_name().unwrap().to_string_lossy().to_string();
+
+                        match &job.state {
+                            JobState::Running { progress, eta, .. } => {
+                                let eta = match eta {
+                                    Some(eta) => format!("{} remaining", format_duration(eta)),
+                                    None => "Estimating time...".to_string()
+                                };
+
+                                ui.label(eta);
+
+                                ui.add(
+                                    egui::ProgressBar::new(*progress)
+                                        .desired_width(200.0)
+                                        .show_percentage()
+                                );
+
+                                ui.label(image_name);
+                            },
+                            state => {
+                                ui.label(format!("{}: {}", image_name, state));
+
+                                ui.add(
+                                    egui::Spinner::new()
+                                        .color(Color32::from_hex("#e05f78").unwrap()) // NOTE: This should be the default accent colour.
+                                        .size(20.0)
+                                );
+                            }
+                        }
+                    });
+                } else if let Ok(loading_status) = self.notifier.loading_status.try_read() {
                     if let Some(loading) = loading_status.as_ref() {
                         ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                             if let Some(message) = &loading.message {
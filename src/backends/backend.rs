@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use crate::{error::{Cause, Error}, upscale::{CancelHandle, JobState, Model, UpscaleOptions}};
+
+// Optional settings a backend understands, the UI hides the ones it doesn't.
+#[derive(Clone, Copy, Default)]
+pub struct Capabilities {
+    pub noise: bool,
+    pub syncgap: bool,
+    pub tuning: bool,
+    // The only scales it can do, None for any from 1 to 16.
+    pub scales: Option<&'static [i32]>
+}
+
+// What a backend found in the models folders. Problems that didn't stop the scan,
+// like a .bin without its .param, end up in `warnings`.
+#[derive(Default)]
+pub struct ModelScan {
+    pub models: Vec<Model>,
+    pub warnings: Vec<Error>
+}
+
+impl From<Vec<Model>> for ModelScan {
+    fn from(models: Vec<Model>) -> Self {
+        Self {
+            models,
+            warnings: Vec::new()
+        }
+    }
+}
+
+pub trait UpscaleBackend: Send + Sync {
+    // Unique name of the backend, models point back to it with `Model::backend`.
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    fn discover_models(&self, folders: &[PathBuf]) -> Result<ModelScan, Error>;
+
+    // Checks the options before anything is run so bad combinations fail early.
+    fn validate(&self, options: &UpscaleOptions) -> Result<(), Error>;
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        options: &UpscaleOptions,
+        cancel_handle: &CancelHandle,
+        on_progress: &mut dyn FnMut(JobState)
+    ) -> Result<(), Error>;
+
+    fn cancel(&self, cancel_handle: &CancelHandle) {
+        cancel_handle.cancel();
+    }
+}
+
+// Globs `pattern` inside `folder`. The folder is escaped so names like "models [old]" aren't
+// read as a pattern, entries that can't be read are skipped and kept as warnings.
+pub fn glob_folder(folder: &Path, pattern: &str, warnings: &mut Vec<Error>) -> Result<Vec<PathBuf>, Error> {
+    let folder_pattern = PathBuf::from(glob::Pattern::escape(&folder.to_string_lossy())).join(pattern);
+
+    let entries = match glob::glob(&folder_pattern.to_string_lossy()) {
+        Ok(entries) => entries,
+        Err(error) => return Err(Error::FailedToScanModels(Some(Cause::source(error)), folder.to_path_buf()))
+    };
+
+    let mut paths = Vec::new();
+
+    for entry in entries {
+        match entry {
+            Ok(path) => paths.push(path),
+            Err(error) => {
+                let path = error.path().to_path_buf();
+                warnings.push(Error::FailedToScanModels(Some(Cause::source(error)), path));
+            }
+        }
+    }
+
+    Ok(paths)
+}
\ No newline at end of file
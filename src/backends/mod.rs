@@ -0,0 +1,7 @@
+pub mod backend;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod ncnn;
+pub mod param;
+pub mod resample;
+pub mod scripted;
\ No newline at end of file
@@ -0,0 +1,635 @@
+use std::{fs, io::{BufRead, BufReader}, path::{Path, PathBuf}, process::{Command, Stdio}};
+
+use crate::{error::{Cause, Error}, integrity::Integrity, upscale::{CancelHandle, JobState, Model, ProgressTracker, UpscaleOptions}};
+
+use super::{backend::{glob_folder, Capabilities, ModelScan, UpscaleBackend}, param};
+
+// How a tool lays out its models on disk.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ModelLayout {
+    // `<name>.bin` + `<name>.param` pairs in one folder, picked with `-m <folder> -n <name>`.
+    Files,
+    // One `models-<name>` folder per model, picked with `-m <folder>`.
+    Folders
+}
+
+// Describes how to find and drive one of the ncnn upscaler command line tools.
+pub struct NcnnProfile {
+    pub name: &'static str,
+    pub binary: &'static str,
+    pub layout: ModelLayout,
+    // Where packages put the models, looked at after the folder next to the binary and
+    // `<prefix>/share/<binary>` (e.g. /usr/share/<binary> for /usr/bin/<binary>).
+    pub system_models_folders: &'static [&'static str],
+    // The `models-*` folders of a `Folders` tool, only these are picked up in shared folders
+    // since waifu2x and Real-CUGAN models look alike. Its own models folder is taken as is.
+    pub shared_models: &'static [&'static str],
+    pub scales: Option<&'static [i32]>,
+
+    pub compression_flag: Option<&'static str>,
+    pub noise_flag: Option<&'static str>,
+    pub syncgap_flag: Option<&'static str>
+}
+
+pub const PROFILES: [NcnnProfile; 4] = [
+    NcnnProfile {
+        name: "upscayl",
+        binary: "upscayl-bin",
+        layout: ModelLayout::Files,
+        system_models_folders: &["/usr/lib/upscayl/models", "/opt/upscayl/models"],
+        shared_models: &[],
+        scales: None,
+
+        compression_flag: Some("-c"),
+        noise_flag: None,
+        syncgap_flag: None
+    },
+    NcnnProfile {
+        name: "realesrgan",
+        binary: "realesrgan-ncnn-vulkan",
+        layout: ModelLayout::Files,
+        system_models_folders: &["/usr/share/realesrgan-ncnn-vulkan/models", "/usr/local/share/realesrgan-ncnn-vulkan/models"],
+        shared_models: &[],
+        scales: Some(&[2, 3, 4]),
+
+        compression_flag: None,
+        noise_flag: None,
+        syncgap_flag: None
+    },
+    NcnnProfile {
+        name: "waifu2x",
+        binary: "waifu2x-ncnn-vulkan",
+        layout: ModelLayout::Folders,
+        system_models_folders: &["/usr/share/waifu2x-ncnn-vulkan", "/usr/local/share/waifu2x-ncnn-vulkan"],
+        shared_models: &["models-cunet", "models-upconv_7_*"],
+        scales: Some(&[1, 2, 4, 8, 16, 32]),
+
+        compression_flag: None,
+        noise_flag: Some("-n"),
+        syncgap_flag: None
+    },
+    NcnnProfile {
+        name: "realcugan",
+        binary: "realcugan-ncnn-vulkan",
+        layout: ModelLayout::Folders,
+        system_models_folders: &["/usr/share/realcugan-ncnn-vulkan", "/usr/local/share/realcugan-ncnn-vulkan"],
+        shared_models: &["models-se", "models-pro", "models-nose"],
+        scales: Some(&[1, 2, 3, 4]),
+
+        compression_flag: None,
+        noise_flag: Some("-n"),
+        syncgap_flag: Some("-c")
+    },
+];
+
+impl NcnnProfile {
+    // How many times the tool goes over the image, printing 0% to 100% each time. waifu2x only
+    // has 2x models and runs them again for every doubling.
+    pub fn passes(&self, scale: i32) -> u32 {
+        match self.name {
+            "waifu2x" if scale > 1 => scale.ilog2(),
+            _ => 1
+        }
+    }
+}
+
+pub struct NcnnBackend {
+    profile: &'static NcnnProfile,
+    cli_path: PathBuf,
+    // None when the tool has no models folder of its own, e.g. when they're kept somewhere else.
+    models_folder: Option<PathBuf>,
+    // Models folders of the other ncnn tools, their models are left to them.
+    other_folders: Vec<PathBuf>
+}
+
+impl NcnnBackend {
+    pub fn profile(binary: &str) -> Option<&'static NcnnProfile> {
+        PROFILES.iter().find(|profile| profile.binary == binary || profile.name == binary)
+    }
+
+    // Picks the profile from the binary's file name, unknown names are treated like upscayl-bin.
+    pub fn profile_for_path(path: &Path) -> &'static NcnnProfile {
+        let binary = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        Self::profile(&binary).unwrap_or(&PROFILES[0])
+    }
+
+    pub fn profile_name(&self) -> &'static str {
+        self.profile.binary
+    }
+
+    pub fn models_folder(&self) -> Option<&Path> {
+        self.models_folder.as_deref()
+    }
+
+    #[cfg(feature = "package")]
+    pub fn new(profile: &'static NcnnProfile) -> Result<Self, Error> {
+        use std::env;
+
+        let executable_path = match env::current_exe() {
+            Ok(path) => path,
+            Err(error) => return Err(Error::FailedToGetCurrentExecutablePath(Some(Cause::source(error))))
+        };
+
+        let tool_path = if cfg!(unix) {
+            executable_path.with_file_name(profile.binary)
+        } else {
+            executable_path.with_file_name(format!("{}.exe", profile.binary))
+        };
+
+        if !tool_path.exists() {
+            return Err(
+                not_found(profile, format!("{} is not with the aeternum executable.", profile.binary))
+            )
+        }
+
+        Self::with_cli_path(profile, tool_path)
+    }
+
+    #[cfg(not(feature = "package"))] // NOTE: This only works on linux.
+    pub fn new(profile: &'static NcnnProfile) -> Result<Self, Error> {
+        use which::which;
+
+        match which(profile.binary) {
+            Ok(path) => Self::with_cli_path(profile, path),
+            Err(err) => Err(not_found(profile, err.to_string()))
+        }
+    }
+
+    pub fn with_cli_path(profile: &'static NcnnProfile, cli_path: PathBuf) -> Result<Self, Error> {
+        if !cli_path.is_file() {
+            return Err(
+                Error::FileNotFound(None, cli_path, format!("{} must be a file.", profile.binary))
+            );
+        }
+
+        let binary_folder = cli_path.parent().unwrap();
+        let mut candidates = vec![binary_folder.to_path_buf()];
+
+        if let Some(prefix) = binary_folder.parent() {
+            candidates.push(prefix.join("share").join(profile.binary));
+        }
+
+        // `Files` tools keep their models in a "models" folder, the models-* folders of the
+        // others sit right in it.
+        if profile.layout == ModelLayout::Files {
+            candidates = candidates.into_iter().map(|folder| folder.join("models")).collect();
+        }
+
+        candidates.extend(profile.system_models_folders.iter().map(PathBuf::from));
+
+        // The first folder that holds models wins, otherwise the first one that exists at all.
+        let models_folder = candidates.iter()
+            .find(|folder| holds_models(profile.layout, folder))
+            .or_else(|| candidates.iter().find(|folder| folder.is_dir()))
+            .cloned();
+
+        if models_folder.is_none() {
+            log::debug!("{} has no models folder, looked in: {:?}", profile.binary, candidates);
+        }
+
+        Ok(Self {
+            profile,
+            cli_path,
+            models_folder,
+            other_folders: Vec::new()
+        })
+    }
+
+    // Leaves the models in these folders out, they belong to other tools.
+    pub fn set_other_folders(&mut self, other_folders: Vec<PathBuf>) {
+        self.other_folders = other_folders;
+    }
+
+    // `own` is set for the tool's own models folder (and its subfolders).
+    fn get_models(&self, folder_path: &Path, own: bool, scan: &mut ModelScan) -> Result<(), Error> {
+        match self.profile.layout {
+            ModelLayout::Files => {
+                for entry_path in glob_folder(folder_path, "*.bin", &mut scan.warnings)? {
+                    let stem = entry_path.file_stem().unwrap().to_string_lossy().to_string();
+
+                    // upscayl ships video models next to the image ones, they aren't meant for stills.
+                    if stem.contains("video") {
+                        continue;
+                    }
+
+                    let param_file = entry_path.with_extension("param");
+
+                    if !param_file.exists() {
+                        scan.warnings.push(Error::OrphanedModelFile(None, entry_path));
+                        continue;
+                    }
+
+                    scan.models.push(
+                        Model {
+                            path: entry_path.clone(),
+                            folder: folder_path.to_path_buf(),
+
+                            name: stem,
+                            backend: self.name().to_string(),
+                            info: None,
+                            native_scale: param::native_scale(&param_file),
+                            size: 0,
+                            integrity: Integrity::Unchecked
+                        }
+                    );
+                }
+
+                for entry_path in glob_folder(folder_path, "*.param", &mut scan.warnings)? {
+                    let stem = entry_path.file_stem().unwrap().to_string_lossy().to_string();
+
+                    if !stem.contains("video") && !entry_path.with_extension("bin").exists() {
+                        scan.warnings.push(Error::OrphanedModelFile(None, entry_path));
+                    }
+                }
+            },
+            ModelLayout::Folders => {
+                let patterns = match own {
+                    true => &["models-*"][..],
+                    false => self.profile.shared_models
+                };
+
+                let mut entries = Vec::new();
+
+                for pattern in patterns {
+                    entries.extend(glob_folder(folder_path, pattern, &mut scan.warnings)?);
+                }
+
+                for entry_path in entries {
+                    if !entry_path.is_dir() {
+                        continue;
+                    }
+
+                    let name = entry_path.file_name().unwrap().to_string_lossy().to_string();
+
+                    scan.models.push(
+                        Model {
+                            path: entry_path.clone(),
+                            folder: entry_path.clone(),
+
+                            name: format!(
+                                "{}-{}", self.profile.name, name.trim_start_matches("models-")
+                            ),
+                            backend: self.name().to_string(),
+                            info: None,
+                            native_scale: None,
+                            size: 0,
+                            integrity: Integrity::Unchecked
+                        }
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn model_exists(&self, model: &Model) -> bool {
+        match self.profile.layout {
+            ModelLayout::Files => model.path.exists() && model.path.with_extension("param").exists(),
+            ModelLayout::Folders => model.path.is_dir()
+        }
+    }
+}
+
+impl UpscaleBackend for NcnnBackend {
+    fn name(&self) -> &str {
+        self.profile.name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            noise: self.profile.noise_flag.is_some(),
+            syncgap: self.profile.syncgap_flag.is_some(),
+            tuning: true,
+            scales: self.profile.scales
+        }
+    }
+
+    // NOTE: The folders handed in already hold this tool's own models folder.
+    fn discover_models(&self, folders: &[PathBuf]) -> Result<ModelScan, Error> {
+        let mut scan = ModelScan::default();
+
+        for folder in folders {
+            let own = self.models_folder.as_ref().is_some_and(|own| folder.starts_with(own));
+
+            if !own && self.other_folders.iter().any(|other| folder.starts_with(other)) {
+                continue;
+            }
+
+            self.get_models(folder, own, &mut scan)?;
+        }
+
+        if scan.models.is_empty() {
+            scan.warnings.push(Error::NcnnToolHasNoModels(None, self.profile.binary.to_string()));
+        }
+
+        Ok(scan)
+    }
+
+    fn validate(&self, options: &UpscaleOptions) -> Result<(), Error> {
+        let model = match &options.model {
+            Some(model) => model,
+            None => return Err(
+                Error::FailedToUpscaleImage(None, "No model was selected.".to_string())
+            )
+        };
+
+        if !self.model_exists(model) {
+            return Err(
+                Error::FailedToUpscaleImage(
+                    None,
+                    format!("The files of the model '{}' no longer exist.", model.name)
+                )
+            );
+        }
+
+        match self.profile.scales {
+            Some(scales) if !scales.contains(&options.scale) => {
+                let scales: Vec<String> = scales.iter().map(|scale| scale.to_string()).collect();
+
+                return Err(
+                    Error::FailedToUpscaleImage(
+                        None,
+                        format!("{} only supports the scales {}.", self.profile.binary, scales.join(", "))
+                    )
+                );
+            },
+            None if !(1..=16).contains(&options.scale) => {
+                return Err(
+                    Error::FailedToUpscaleImage(None, "Scale must be between 1 and 16.".to_string())
+                );
+            },
+            _ => {}
+        }
+
+        if self.profile.noise_flag.is_some() && !(-1..=3).contains(&options.noise) {
+            return Err(
+                Error::FailedToUpscaleImage(None, "Noise level must be between -1 and 3.".to_string())
+            );
+        }
+
+        if self.profile.syncgap_flag.is_some() && !(0..=3).contains(&options.syncgap) {
+            return Err(
+                Error::FailedToUpscaleImage(None, "Sync gap mode must be between 0 and 3.".to_string())
+            );
+        }
+
+        let tuning = &options.tuning;
+
+        if tuning.tile_size != 0 && tuning.tile_size < 32 {
+            return Err(
+                Error::FailedToUpscaleImage(None, "Tile size must be 0 (automatic) or at least 32.".to_string())
+            );
+        }
+
+        if tuning.threads.contains(&0) {
+            return Err(
+                Error::FailedToUpscaleImage(None, "Load, process and save threads must all be at least 1.".to_string())
+            );
+        }
+
+        match tuning.gpu_id {
+            Some(gpu_id) if gpu_id < -1 => return Err(
+                Error::FailedToUpscaleImage(None, "GPU id must be -1 (CPU) or higher.".to_string())
+            ),
+            Some(-1) => {
+                // On the CPU device the processing threads are CPU threads.
+                let cpu_count = std::thread::available_parallelism().map_or(1, |count| count.get()) as u32;
+
+                if tuning.threads[1] > cpu_count {
+                    return Err(
+                        Error::FailedToUpscaleImage(
+                            None,
+                            format!("Upscaling on the CPU can use at most {} processing threads on this machine.", cpu_count)
+                        )
+                    );
+                }
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        options: &UpscaleOptions,
+        cancel_handle: &CancelHandle,
+        on_progress: &mut dyn FnMut(JobState)
+    ) -> Result<(), Error> {
+        let mut upscale_command = Command::new(self.cli_path.to_string_lossy().to_string());
+
+        #[cfg(target_os = "windows")] {
+            use std::os::windows::process::CommandExt;
+
+            upscale_command.creation_flags(0x08000000);
+        }
+
+        // NOTE: validate() makes sure a model is set.
+        let model = options.model.as_ref().unwrap();
+
+        upscale_command.args([
+            "-i",
+            &input.to_string_lossy(),
+            "-o",
+            &output.to_string_lossy(),
+            "-m",
+            &model.folder.to_string_lossy(),
+            "-s",
+            &options.scale.to_string()
+        ]);
+
+        if self.profile.layout == ModelLayout::Files {
+            upscale_command.args(["-n", &model.name]);
+        }
+
+        if let Some(flag) = self.profile.compression_flag {
+            upscale_command.args([flag, &options.compression.to_string()]);
+        }
+
+        if let Some(flag) = self.profile.noise_flag {
+            upscale_command.args([flag, &options.noise.to_string()]);
+        }
+
+        if let Some(flag) = self.profile.syncgap_flag {
+            upscale_command.args([flag, &options.syncgap.to_string()]);
+        }
+
+        let tuning = &options.tuning;
+
+        upscale_command.args([
+            "-t",
+            &tuning.tile_size.to_string(),
+            "-j",
+            &tuning.threads.map(|threads| threads.to_string()).join(":")
+        ]);
+
+        if tuning.tta {
+            upscale_command.arg("-x");
+        }
+
+        if let Some(gpu_id) = tuning.gpu_id {
+            upscale_command.args(["-g", &gpu_id.to_string()]);
+        }
+
+        let cmd = upscale_command
+            .stderr(Stdio::piped()) // why do you output to stderr :woe: ~ Ananas
+            .spawn();
+
+        let mut child = match cmd {
+            Ok(child) => child,
+            Err(error) => return Err(
+                Error::FailedToUpscaleImage(Some(Cause::source(error)), "Failed to spawn child process.".to_string())
+            )
+        };
+
+        let stderr = child.stderr.take();
+        let mut log = Vec::new();
+
+        cancel_handle.set_child(child);
+
+        if let Some(stderr) = stderr {
+            let reader = BufReader::new(stderr);
+
+            let mut tracker = ProgressTracker::new(self.profile.passes(options.scale));
+
+            for line in reader.lines().map_while(Result::ok) {
+                // The ncnn tools report progress as lines like "12.34%", everything else is kept for errors.
+                match line.trim().strip_suffix('%').and_then(|percent| percent.parse::<f32>().ok()) {
+                    Some(percent) => on_progress(tracker.update(percent)),
+                    None => log.push(line)
+                }
+            }
+        }
+
+        let child = cancel_handle.take_child();
+        let status = child.unwrap().wait();
+
+        if cancel_handle.is_cancelled() {
+            if output.exists() {
+                if let Err(error) = fs::remove_file(output) {
+                    log::warn!("Failed to delete partial output '{}': {}", output.display(), error);
+                }
+            }
+
+            return Err(Error::UpscaleCancelled(None));
+        }
+
+        // Some tools still exit successfully after a failure, leaving a broken image behind.
+        if let Some(error) = classify_failure(&log, input, output, model) {
+            if output.exists() {
+                if let Err(error) = fs::remove_file(output) {
+                    log::warn!("Failed to delete broken output '{}': {}", output.display(), error);
+                }
+            }
+
+            return Err(error);
+        }
+
+        match status {
+            Ok(status) => {
+                if !status.success() {
+                    Err(
+                        Error::FailedToUpscaleImage(
+                            Some(log_tail(&log).into()),
+                            "Process returned as not successful.".to_string()
+                        )
+                    )
+                } else if !output.exists() {
+                    Err(Error::FailedToWriteOutput(Some(log_tail(&log).into()), output.to_path_buf()))
+                } else {
+                    Ok(())
+                }
+            },
+            Err(error) => Err(
+                Error::FailedToUpscaleImage(
+                    Some(Cause::source(error)),
+                    "Failed to wait for process.".to_string()
+                )
+            )
+        }
+    }
+}
+
+fn holds_models(layout: ModelLayout, folder: &Path) -> bool {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return false
+    };
+
+    entries.filter_map(Result::ok).any(|entry| {
+        let path = entry.path();
+
+        match layout {
+            ModelLayout::Files => path.extension().is_some_and(|extension| extension == "bin"),
+            ModelLayout::Folders => path.is_dir() && entry.file_name().to_string_lossy().starts_with("models-")
+        }
+    })
+}
+
+fn not_found(profile: &NcnnProfile, detail: String) -> Error {
+    match profile.name {
+        "upscayl" => Error::UpscaylNotInPath(Some(detail.into())),
+        _ => Error::NcnnToolNotFound(Some(detail.into()), profile.binary.to_string())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Failure {
+    OutOfMemory,
+    Vulkan,
+    Model,
+    Decode,
+    Encode
+}
+
+// Lowercased snippets the ncnn tools print to stderr when they fail.
+const FAILURES: [(&str, Failure); 13] = [
+    ("vkallocatememory failed", Failure::OutOfMemory),
+    ("vk_error_out_of_device_memory", Failure::OutOfMemory),
+    ("vk_error_out_of_host_memory", Failure::OutOfMemory),
+    ("out of memory", Failure::OutOfMemory),
+    ("bad_alloc", Failure::OutOfMemory),
+    ("vkcreateinstance failed", Failure::Vulkan),
+    ("invalid gpu device", Failure::Vulkan),
+    ("vkenumeratephysicaldevices failed", Failure::Vulkan),
+    ("network graph not ready", Failure::Model),
+    ("param is too old", Failure::Model),
+    ("decode image", Failure::Decode),
+    ("unknown format", Failure::Decode),
+    ("encode image", Failure::Encode),
+];
+
+fn classify_failure(log: &[String], input: &Path, output: &Path, model: &Model) -> Option<Error> {
+    let failure = log.iter().find_map(|line| {
+        let line = line.to_lowercase();
+
+        // ncnn prints "fopen <file> failed" when the .param or .bin can't be opened.
+        if line.contains("fopen") && line.contains("failed") {
+            return Some(Failure::Model);
+        }
+
+        FAILURES.iter()
+            .find(|(pattern, _)| line.contains(pattern))
+            .map(|(_, failure)| *failure)
+    })?;
+
+    let detail = Some(log_tail(log).into());
+
+    let error = match failure {
+        Failure::OutOfMemory => Error::UpscaleOutOfMemory(detail),
+        Failure::Vulkan => Error::VulkanUnavailable(detail),
+        Failure::Model => Error::ModelFailedToLoad(detail, model.name.clone()),
+        Failure::Decode => Error::UnsupportedInputImage(detail, input.to_path_buf()),
+        Failure::Encode => Error::FailedToWriteOutput(detail, output.to_path_buf())
+    };
+
+    Some(error)
+}
+
+fn log_tail(log: &[String]) -> String {
+    log[log.len().saturating_sub(10)..].join("\n")
+}
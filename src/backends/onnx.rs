@@ -0,0 +1,199 @@
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use ::image::{imageops::FilterType, DynamicImage, GrayImage, RgbImage, RgbaImage};
+use tract_onnx::prelude::*;
+
+use crate::{error::{Cause, Error}, integrity::Integrity, upscale::{CancelHandle, JobState, Model, ProgressTracker, UpscaleOptions}};
+
+use super::{backend::{glob_folder, ModelScan, UpscaleBackend}, resample::save_image};
+
+const TILE_SIZE: u32 = 128;
+const TILE_OVERLAP: u32 = 16;
+
+// Runs ONNX models (e.g. from OpenModelDB) in-process on the CPU with tract.
+pub struct OnnxBackend;
+
+impl OnnxBackend {
+    fn get_models(&self, folder_path: &Path, scan: &mut ModelScan) -> Result<(), Error> {
+        for entry_path in glob_folder(folder_path, "*.onnx", &mut scan.warnings)? {
+            scan.models.push(
+                Model {
+                    path: entry_path.clone(),
+                    folder: folder_path.to_path_buf(),
+
+                    name: entry_path.file_stem().unwrap().to_string_lossy().to_string(),
+                    backend: self.name().to_string(),
+                    info: None,
+                    native_scale: None,
+                    size: 0,
+                    integrity: Integrity::Unchecked
+                }
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl UpscaleBackend for OnnxBackend {
+    fn name(&self) -> &str {
+        "onnx"
+    }
+
+    fn discover_models(&self, folders: &[PathBuf]) -> Result<ModelScan, Error> {
+        let mut scan = ModelScan::default();
+
+        for folder in folders {
+            self.get_models(folder, &mut scan)?;
+        }
+
+        Ok(scan)
+    }
+
+    fn validate(&self, options: &UpscaleOptions) -> Result<(), Error> {
+        match &options.model {
+            Some(model) if model.path.exists() => Ok(()),
+            Some(model) => Err(
+                Error::FailedToUpscaleImage(
+                    None,
+                    format!("The file of the model '{}' no longer exists.", model.name)
+                )
+            ),
+            None => Err(
+                Error::FailedToUpscaleImage(None, "No model was selected.".to_string())
+            )
+        }
+    }
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        options: &UpscaleOptions,
+        cancel_handle: &CancelHandle,
+        on_progress: &mut dyn FnMut(JobState)
+    ) -> Result<(), Error> {
+        let model = options.model.as_ref().unwrap();
+        let window = TILE_SIZE + TILE_OVERLAP * 2;
+
+        let runnable = tract_onnx::onnx()
+            .model_for_path(&model.path)
+            .and_then(|graph| graph.with_input_fact(0, f32::fact([1, 3, window as usize, window as usize]).into()))
+            .and_then(|graph| graph.into_optimized())
+            .and_then(|graph| graph.into_runnable())
+            .map_err(|error| tract_error(error, "Failed to load the onnx model."))?;
+
+        let image = match ::image::open(input) {
+            Ok(image) => image,
+            Err(error) => return Err(
+                Error::FailedToUpscaleImage(Some(Cause::source(error)), "Failed to read the input image.".to_string())
+            )
+        };
+
+        let rgb = image.to_rgb8();
+        let (width, height) = rgb.dimensions();
+
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        let tile_count = tiles_x * tiles_y;
+
+        let mut tracker = ProgressTracker::new(1);
+        let mut native_scale = 0;
+        let mut upscaled = RgbImage::new(0, 0);
+
+        on_progress(tracker.update(0.0));
+
+        for tile in 0..tile_count {
+            if cancel_handle.is_cancelled() {
+                return Err(Error::UpscaleCancelled(None));
+            }
+
+            let tile_x = (tile % tiles_x) * TILE_SIZE;
+            let tile_y = (tile / tiles_x) * TILE_SIZE;
+
+            // Pixels outside the image are clamped to the edge so every window has the same size.
+            let tensor: Tensor = tract_ndarray::Array4::from_shape_fn(
+                (1, 3, window as usize, window as usize),
+                |(_, channel, y, x)| {
+                    let x = (tile_x as i64 + x as i64 - TILE_OVERLAP as i64).clamp(0, width as i64 - 1);
+                    let y = (tile_y as i64 + y as i64 - TILE_OVERLAP as i64).clamp(0, height as i64 - 1);
+
+                    rgb.get_pixel(x as u32, y as u32)[channel] as f32 / 255.0
+                }
+            ).into();
+
+            let result = runnable.run(tvec!(tensor.into()))
+                .map_err(|error| tract_error(error, "Failed to run the onnx model."))?;
+
+            let result = result[0].to_array_view::<f32>()
+                .map_err(|error| tract_error(error, "The onnx model returned an unexpected output."))?;
+
+            if result.ndim() != 4 || result.shape()[1] != 3 {
+                return Err(
+                    Error::FailedToUpscaleImage(
+                        None,
+                        format!("The onnx model returned a tensor of shape {:?}, expected [1, 3, height, width].", result.shape())
+                    )
+                );
+            }
+
+            if native_scale == 0 {
+                native_scale = (result.shape()[3] as u32 / window).max(1);
+                upscaled = RgbImage::new(width * native_scale, height * native_scale);
+            }
+
+            // Only the inner part of each window is kept, the overlap hides the seams between tiles.
+            let inner_width = TILE_SIZE.min(width - tile_x) * native_scale;
+            let inner_height = TILE_SIZE.min(height - tile_y) * native_scale;
+            let offset = TILE_OVERLAP * native_scale;
+
+            for y in 0..inner_height {
+                for x in 0..inner_width {
+                    let pixel = upscaled.get_pixel_mut(tile_x * native_scale + x, tile_y * native_scale + y);
+
+                    for channel in 0..3 {
+                        let value = result[[0, channel, (offset + y) as usize, (offset + x) as usize]];
+
+                        pixel[channel] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                }
+            }
+
+            on_progress(tracker.update((tile + 1) as f32 / tile_count as f32 * 100.0));
+        }
+
+        let (target_width, target_height) = (width * options.scale.max(1) as u32, height * options.scale.max(1) as u32);
+
+        let mut upscaled = DynamicImage::ImageRgb8(upscaled);
+
+        if upscaled.width() != target_width {
+            upscaled = upscaled.resize_exact(target_width, target_height, FilterType::Lanczos3);
+        }
+
+        // Models only handle colour, so the alpha channel is resized on its own.
+        if image.color().has_alpha() {
+            let rgba = image.to_rgba8();
+            let alpha = GrayImage::from_fn(width, height, |x, y| ::image::Luma([rgba.get_pixel(x, y)[3]]));
+
+            let alpha = DynamicImage::ImageLuma8(alpha)
+                .resize_exact(target_width, target_height, FilterType::Triangle)
+                .to_luma8();
+
+            let rgb = upscaled.to_rgb8();
+
+            upscaled = DynamicImage::ImageRgba8(
+                RgbaImage::from_fn(target_width, target_height, |x, y| {
+                    let [r, g, b] = rgb.get_pixel(x, y).0;
+
+                    ::image::Rgba([r, g, b, alpha.get_pixel(x, y)[0]])
+                })
+            );
+        }
+
+        save_image(upscaled, output, options)
+    }
+}
+
+fn tract_error(error: impl Display, reason: &str) -> Error {
+    Error::FailedToUpscaleImage(Some(error.to_string().into()), reason.to_string())
+}
@@ -0,0 +1,217 @@
+use std::{collections::HashMap, fs, path::Path};
+
+// First line of every ncnn .param file in the text format.
+const MAGIC: &str = "7767517";
+
+// Works out the factor an ncnn model upscales by from the layer graph in its .param file. Layers
+// that grow the image multiply it, strided convolutions divide it. None when the file can't be read
+// or the layers don't add up to a whole factor.
+pub fn native_scale(param_path: &Path) -> Option<u32> {
+    let content = match fs::read_to_string(param_path) {
+        Ok(content) => content,
+        Err(error) => {
+            log::debug!("Failed to read '{}' for its scale: {}", param_path.display(), error);
+            return None;
+        }
+    };
+
+    match scale_of(&content) {
+        Ok(scale) => Some(scale),
+        Err(reason) => {
+            log::debug!("'{}' {}", param_path.display(), reason);
+            None
+        }
+    }
+}
+
+// The scale of the model in a .param file's content, or why it can't be worked out. Every blob
+// carries the scale it's at, starting at 1 for the input, so branches that meet again in a layer
+// (e.g. a residual upscaled next to the network) aren't counted twice.
+fn scale_of(content: &str) -> Result<u32, String> {
+    let mut lines = content.lines();
+
+    if lines.next().map(str::trim) != Some(MAGIC) {
+        return Err("isn't a text ncnn .param file.".to_string());
+    }
+
+    // Skips the layer and blob counts.
+    lines.next();
+
+    let mut scales: HashMap<&str, f64> = HashMap::new();
+    let mut output = None;
+
+    for line in lines {
+        let mut fields = line.split_whitespace();
+
+        let (layer_type, layer_name) = match (fields.next(), fields.next()) {
+            (Some(layer_type), Some(layer_name)) => (layer_type, layer_name),
+            _ => continue
+        };
+
+        // The input and output blob counts followed by the blobs themselves.
+        let mut count = || fields.next().and_then(|count| count.parse::<usize>().ok()).unwrap_or(0);
+        let (input_count, output_count) = (count(), count());
+
+        let inputs: Vec<&str> = fields.by_ref().take(input_count).collect();
+        let outputs: Vec<&str> = fields.by_ref().take(output_count).collect();
+
+        let params: Vec<(i32, &str)> = fields
+            .filter_map(|param| param.split_once('='))
+            .filter_map(|(key, value)| key.parse().ok().map(|key| (key, value)))
+            .collect();
+
+        let param = |key: i32, default: f64| -> f64 {
+            params.iter()
+                .find(|(param_key, _)| *param_key == key)
+                .and_then(|(_, value)| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        // 3=output_height and 5=dynamic_target_size resize to a size rather than by a factor.
+        if layer_type == "Interp" && (param(3, 0.0) != 0.0 || param(5, 0.0) != 0.0) {
+            return Err("resizes to a size, its scale can't be worked out.".to_string());
+        }
+
+        let factor = match layer_type {
+            // 0=upscale_factor
+            "PixelShuffle" => param(0, 1.0),
+            // 1=height_scale
+            "Interp" => param(1, 1.0),
+            // 3=stride_w
+            "Deconvolution" | "DeconvolutionDepthWise" => param(3, 1.0),
+            "Convolution" | "ConvolutionDepthWise" => 1.0 / param(3, 1.0),
+            // 0=stride, the pixel unshuffle some 2x and 1x models start with.
+            "Reorg" => 1.0 / param(0, 1.0),
+            _ => 1.0
+        };
+
+        // Blobs that aren't listed before they're used are left out, e.g. in a trimmed file.
+        let mut input_scales = inputs.iter().filter_map(|input| scales.get(input).copied());
+        let input_scale = input_scales.next().unwrap_or(1.0);
+
+        if input_scales.any(|scale| (scale - input_scale).abs() >= 0.01) {
+            return Err(format!("joins blobs of different scales in '{}', ignoring it.", layer_name));
+        }
+
+        // Constants are at no scale at all, they're broadcast to whatever they're used with.
+        if layer_type != "MemoryData" {
+            for blob in outputs.iter() {
+                scales.insert(blob, input_scale * factor);
+            }
+        }
+
+        if let Some(blob) = outputs.first() {
+            output = Some(*blob);
+        }
+    }
+
+    let scale = match output.and_then(|output| scales.get(output)) {
+        Some(scale) => *scale,
+        None => return Err("has no layers.".to_string())
+    };
+
+    let rounded = scale.round();
+
+    match rounded >= 1.0 && (scale - rounded).abs() < 0.01 {
+        true => Ok(rounded as u32),
+        false => Err(format!("works out to a scale of {}, ignoring it.", scale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scale_of;
+
+    // Trimmed down from the realesrgan-x4plus.param upscayl ships, two 2x nearest Interps.
+    const INTERP_4X: &str = "7767517
+8 9
+Input            data             0 1 data
+Convolution      Conv_0           1 1 data 703 0=64 1=3 4=1 5=1 6=1728
+Split            splitncnn_0      1 2 703 703_splitncnn_0 703_splitncnn_1
+BinaryOp         Add_1120         2 1 703_splitncnn_0 1007 1008 0=0
+Interp           Resize_1124      1 1 1008 1013 0=1 1=2.000000e+00 2=2.000000e+00
+Convolution      Conv_1125        1 1 1013 1014 0=64 1=3 4=1 5=1 6=36864
+ReLU             LeakyRelu_1126   1 1 1014 1015 0=2.000000e-01
+Interp           Resize_1127      1 1 1015 1020 0=1 1=2.000000e+00 2=2.000000e+00
+";
+
+    #[test]
+    fn interp_models() {
+        assert_eq!(scale_of(INTERP_4X), Ok(4));
+    }
+
+    #[test]
+    fn pixel_shuffle_models() {
+        let param = "7767517
+3 3
+Input            input            0 1 input
+Convolution      Conv_0           1 1 input 33 0=48 1=3 4=1 5=1 6=1296
+PixelShuffle     PixelShuffle_30  1 1 33 output 0=4
+";
+
+        assert_eq!(scale_of(param), Ok(4));
+    }
+
+    #[test]
+    fn unshuffled_models() {
+        // x2plus unshuffles the input by 2 before the same 4x tail.
+        let reorg = INTERP_4X.replacen(
+            "Input            data             0 1 data\n",
+            "Input            data             0 1 data\nReorg            Reorg_0          1 1 data data 0=2\n",
+            1
+        );
+
+        assert_eq!(scale_of(&reorg), Ok(2));
+
+        // A strided convolution taking back a 2x upscale makes a 1x model.
+        let strided = "7767517
+3 3
+Input            data             0 1 data
+Convolution      Conv_0           1 1 data 1 0=64 1=3 3=2 4=1 5=1 6=1728
+Interp           Resize_0         1 1 1 output 0=1 1=2.000000e+00 2=2.000000e+00
+";
+
+        assert_eq!(scale_of(strided), Ok(1));
+    }
+
+    // Like realesr-animevideov3, the network's output is added to the input upscaled on its own.
+    const RESIDUAL_4X: &str = "7767517
+6 7
+Input            data             0 1 data
+Split            splitncnn_0      1 2 data data_splitncnn_0 data_splitncnn_1
+Convolution      Conv_0           1 1 data_splitncnn_1 1 0=48 1=3 4=1 5=1 6=1296
+PixelShuffle     PixelShuffle_0   1 1 1 2 0=4
+Interp           Resize_0         1 1 data_splitncnn_0 3 0=1 1=4.000000e+00 2=4.000000e+00
+BinaryOp         Add_0            2 1 2 3 output 0=0
+";
+
+    #[test]
+    fn residual_models() {
+        assert_eq!(scale_of(RESIDUAL_4X), Ok(4));
+
+        let mismatched = RESIDUAL_4X.replace("1=4.000000e+00 2=4.000000e+00", "1=2.000000e+00 2=2.000000e+00");
+
+        assert!(scale_of(&mismatched).is_err());
+    }
+
+    #[test]
+    fn unknown_scales() {
+        assert!(scale_of("7767518\n0 0\n").is_err());
+
+        let fixed_size = "7767517
+2 2
+Input            data             0 1 data
+Interp           Resize_0         1 1 data output 0=2 3=512 4=512
+";
+
+        assert!(scale_of(fixed_size).is_err());
+
+        let fractional = "7767517
+2 2
+Input            data             0 1 data
+Interp           Resize_0         1 1 data output 0=2 1=1.500000e+00 2=1.500000e+00
+";
+
+        assert!(scale_of(fractional).is_err());
+    }
+}
@@ -0,0 +1,425 @@
+use std::{fs::File, io::BufWriter, path::{Path, PathBuf}};
+
+use ::image::{codecs::{jpeg::JpegEncoder, png::{self, PngEncoder}}, imageops::FilterType, DynamicImage, ImageFormat, Rgba, RgbaImage};
+
+use crate::{error::{Cause, Error}, integrity::Integrity, upscale::{CancelHandle, JobState, Model, OutputExt, ProgressTracker, UpscaleOptions}};
+
+use super::backend::{ModelScan, UpscaleBackend};
+
+// In-process backend built on the image crate, it needs no external binary or GPU.
+pub struct ResampleBackend;
+
+#[derive(Clone, Copy)]
+enum Method {
+    Filter(FilterType),
+    Scale2x,
+    Scale3x,
+    Smooth(u32)
+}
+
+const METHODS: [(&str, Method); 9] = [
+    ("cpu-nearest", Method::Filter(FilterType::Nearest)),
+    ("cpu-triangle", Method::Filter(FilterType::Triangle)),
+    ("cpu-catmull-rom", Method::Filter(FilterType::CatmullRom)),
+    ("cpu-lanczos3", Method::Filter(FilterType::Lanczos3)),
+    ("cpu-scale2x", Method::Scale2x),
+    ("cpu-scale3x", Method::Scale3x),
+    ("cpu-smooth2x", Method::Smooth(2)),
+    ("cpu-smooth3x", Method::Smooth(3)),
+    ("cpu-smooth4x", Method::Smooth(4)),
+];
+
+// Thresholds hqx uses to tell colours apart, on Y, U, V and (on top of hqx) alpha.
+const SMOOTH_THRESHOLDS: [f32; 4] = [48.0, 7.0, 6.0, 48.0];
+
+impl ResampleBackend {
+    fn method(&self, model: &Model) -> Result<Method, Error> {
+        match METHODS.iter().find(|(name, _)| *name == model.name) {
+            Some((_, method)) => Ok(*method),
+            None => Err(
+                Error::FailedToUpscaleImage(None, format!("'{}' is not a resampling method.", model.name))
+            )
+        }
+    }
+}
+
+impl UpscaleBackend for ResampleBackend {
+    fn name(&self) -> &str {
+        "resample"
+    }
+
+    fn discover_models(&self, _folders: &[PathBuf]) -> Result<ModelScan, Error> {
+        Ok(
+            METHODS.iter().map(|(name, _)| Model {
+                path: PathBuf::new(),
+                folder: PathBuf::new(),
+
+                name: name.to_string(),
+                backend: self.name().to_string(),
+                info: None,
+                native_scale: None,
+                size: 0,
+                integrity: Integrity::Unchecked
+            }).collect::<Vec<_>>().into()
+        )
+    }
+
+    fn validate(&self, options: &UpscaleOptions) -> Result<(), Error> {
+        match &options.model {
+            Some(model) => self.method(model).map(|_| ()),
+            None => Err(
+                Error::FailedToUpscaleImage(None, "No model was selected.".to_string())
+            )
+        }
+    }
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        options: &UpscaleOptions,
+        cancel_handle: &CancelHandle,
+        on_progress: &mut dyn FnMut(JobState)
+    ) -> Result<(), Error> {
+        let method = self.method(options.model.as_ref().unwrap())?;
+
+        let image = match ::image::open(input) {
+            Ok(image) => image,
+            Err(error) => return Err(
+                Error::FailedToUpscaleImage(Some(Cause::source(error)), "Failed to read the input image.".to_string())
+            )
+        };
+
+        let scale = options.scale.max(1) as u32;
+        let (width, height) = (image.width() * scale, image.height() * scale);
+
+        let upscaled = match method {
+            Method::Filter(filter) => {
+                let mut tracker = ProgressTracker::new(1);
+
+                on_progress(tracker.update(0.0));
+                let resized = image.resize_exact(width, height, filter);
+                on_progress(tracker.update(100.0));
+
+                resized
+            },
+            Method::Scale2x | Method::Scale3x | Method::Smooth(_) => {
+                let factor = match method {
+                    Method::Scale2x => 2,
+                    Method::Smooth(factor) => factor,
+                    _ => 3
+                };
+
+                // Pixel art scalers only do their own factor, so they are repeated as
+                // often as they fit and nearest neighbour covers whatever is left.
+                let mut passes = 0;
+                let mut reached = 1;
+
+                while reached * factor <= scale {
+                    reached *= factor;
+                    passes += 1;
+                }
+
+                let mut tracker = ProgressTracker::new(passes);
+                let mut pixels = image.to_rgba8();
+
+                for _ in 0..passes {
+                    let block = match method {
+                        Method::Smooth(_) => smooth_block,
+                        _ => epx_block
+                    };
+
+                    pixels = scale_pixels(&pixels, factor, block, cancel_handle, &mut |percent| {
+                        on_progress(tracker.update(percent))
+                    })?;
+                }
+
+                let upscaled = DynamicImage::ImageRgba8(pixels);
+
+                match upscaled.width() == width {
+                    true => upscaled,
+                    false => upscaled.resize_exact(width, height, FilterType::Nearest)
+                }
+            }
+        };
+
+        save_image(upscaled, output, options)
+    }
+}
+
+// Writes the image in the format chosen in the options, honouring the compression slider.
+pub fn save_image(image: DynamicImage, output: &Path, options: &UpscaleOptions) -> Result<(), Error> {
+    let file = match File::create(output) {
+        Ok(file) => BufWriter::new(file),
+        Err(error) => return Err(Error::FailedToWriteOutput(Some(Cause::source(error)), output.to_path_buf()))
+    };
+
+    let result = match options.output_ext {
+        OutputExt::JPG => {
+            // JPEG can't hold an alpha channel.
+            let quality = (100 - options.compression).clamp(1, 100) as u8;
+
+            image.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(file, quality))
+        },
+        OutputExt::PNG => {
+            let compression = match options.compression {
+                0 => png::CompressionType::Default,
+                1..=50 => png::CompressionType::Fast,
+                _ => png::CompressionType::Best
+            };
+
+            image.write_with_encoder(
+                PngEncoder::new_with_quality(file, compression, png::FilterType::Adaptive)
+            )
+        },
+        OutputExt::WebP => {
+            let mut file = file;
+
+            image.to_rgba8().write_to(&mut file, ImageFormat::WebP)
+        }
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) => Err(Error::FailedToWriteOutput(Some(Cause::source(error)), output.to_path_buf()))
+    }
+}
+
+// Neighbours of the pixel being scaled, `pixel(0, 0)` is the pixel itself. Edges are repeated.
+type Neighbours<'a> = dyn Fn(i64, i64) -> Rgba<u8> + 'a;
+
+// Runs a pixel art scaler over every pixel, `block` turns one into `factor` x `factor` pixels.
+fn scale_pixels(
+    source: &RgbaImage,
+    factor: u32,
+    block: fn(&Neighbours, u32) -> Vec<Rgba<u8>>,
+    cancel_handle: &CancelHandle,
+    on_progress: &mut dyn FnMut(f32)
+) -> Result<RgbaImage, Error> {
+    let (width, height) = source.dimensions();
+    let mut target = RgbaImage::new(width * factor, height * factor);
+    let mut last_percent = 0;
+
+    for y in 0..height {
+        if cancel_handle.is_cancelled() {
+            return Err(Error::UpscaleCancelled(None));
+        }
+
+        for x in 0..width {
+            let pixel = |dx: i64, dy: i64| -> Rgba<u8> {
+                *source.get_pixel(
+                    (x as i64 + dx).clamp(0, width as i64 - 1) as u32,
+                    (y as i64 + dy).clamp(0, height as i64 - 1) as u32
+                )
+            };
+
+            for (index, colour) in block(&pixel, factor).into_iter().enumerate() {
+                let index = index as u32;
+
+                target.put_pixel(
+                    x * factor + index % factor,
+                    y * factor + index / factor,
+                    colour
+                );
+            }
+        }
+
+        let percent = (y + 1) * 100 / height;
+
+        if percent != last_percent {
+            last_percent = percent;
+            on_progress(percent as f32);
+        }
+    }
+
+    Ok(target)
+}
+
+// Scale2x / Scale3x (also known as EPX), they only fill in corners along hard edges so pixel art stays sharp.
+fn epx_block(pixel: &Neighbours, factor: u32) -> Vec<Rgba<u8>> {
+    let a = pixel(-1, -1);
+    let b = pixel(0, -1);
+    let c = pixel(1, -1);
+    let d = pixel(-1, 0);
+    let e = pixel(0, 0);
+    let f = pixel(1, 0);
+    let g = pixel(-1, 1);
+    let h = pixel(0, 1);
+    let i = pixel(1, 1);
+
+    match factor {
+        2 => {
+            if b != h && d != f {
+                vec![
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                vec![e; 4]
+            }
+        },
+        _ => {
+            if b != h && d != f {
+                vec![
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) { b } else { e },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) { d } else { e },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) { f } else { e },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) { h } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                vec![e; 9]
+            }
+        }
+    }
+}
+
+// An edge smoothing pixel art scaler in the spirit of hqx, not hqx itself. Neighbours are compared
+// in YUV like hqx does, but instead of its hand made lookup tables every output pixel is blended
+// from where it sits in the source pixel: corners cut by an edge take on the colour across it
+// (anti-aliased by how much of them is past the edge), a lone differing diagonal is blended in
+// slightly and straight edges are softened.
+fn smooth_block(pixel: &Neighbours, factor: u32) -> Vec<Rgba<u8>> {
+    let centre = pixel(0, 0);
+    let size = 2.0 / factor as f32;
+    let mut block = Vec::with_capacity((factor * factor) as usize);
+
+    for row in 0..factor {
+        for column in 0..factor {
+            // Middle of the output pixel inside the source pixel, from -1 to 1 on both axes.
+            let x = (2 * column + 1) as f32 / factor as f32 - 1.0;
+            let y = (2 * row + 1) as f32 / factor as f32 - 1.0;
+
+            let (dx, dy) = (side(x), side(y));
+
+            let horizontal = pixel(dx, 0);
+            let vertical = pixel(0, dy);
+            let horizontal_differs = dx != 0 && differs(centre, horizontal);
+            let vertical_differs = dy != 0 && differs(centre, vertical);
+
+            // Like EPX, a corner is only cut when the pixel isn't part of a straight line.
+            let edge = horizontal_differs && vertical_differs
+                && !differs(horizontal, vertical)
+                && differs(horizontal, pixel(-dx, 0))
+                && differs(vertical, pixel(0, -dy));
+
+            let colour = if edge {
+                let coverage = ((x.abs() + y.abs() - 1.0) / size + 0.5).clamp(0.0, 1.0);
+
+                mix(centre, mix(horizontal, vertical, 0.5), coverage)
+            } else if dx != 0 && dy != 0 && !horizontal_differs && !vertical_differs && differs(centre, pixel(dx, dy)) {
+                mix(centre, pixel(dx, dy), x.abs().min(y.abs()) / 2.0)
+            } else {
+                let mut colour = centre;
+
+                if horizontal_differs {
+                    colour = mix(colour, horizontal, x.abs() / 4.0);
+                }
+
+                if vertical_differs {
+                    colour = mix(colour, vertical, y.abs() / 4.0);
+                }
+
+                colour
+            };
+
+            block.push(colour);
+        }
+    }
+
+    block
+}
+
+fn side(position: f32) -> i64 {
+    match position {
+        position if position < 0.0 => -1,
+        position if position > 0.0 => 1,
+        _ => 0
+    }
+}
+
+fn differs(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    let (a, b) = (yuva(a), yuva(b));
+
+    (0..4).any(|index| (a[index] - b[index]).abs() > SMOOTH_THRESHOLDS[index])
+}
+
+fn yuva(colour: Rgba<u8>) -> [f32; 4] {
+    let [r, g, b, a] = colour.0.map(|channel| channel as f32);
+
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.169 * r - 0.331 * g + 0.5 * b + 128.0,
+        0.5 * r - 0.419 * g - 0.081 * b + 128.0,
+        a
+    ]
+}
+
+// Blends `weight` of `b` into `a`. Colours are weighted by their alpha so transparent pixels
+// don't darken the edges of sprites.
+fn mix(a: Rgba<u8>, b: Rgba<u8>, weight: f32) -> Rgba<u8> {
+    let (a_alpha, b_alpha) = (a[3] as f32 * (1.0 - weight), b[3] as f32 * weight);
+    let alpha = a_alpha + b_alpha;
+
+    if alpha == 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let channel = |index: usize| ((a[index] as f32 * a_alpha + b[index] as f32 * b_alpha) / alpha).round() as u8;
+
+    Rgba([channel(0), channel(1), channel(2), alpha.round() as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use ::image::{Rgba, RgbaImage};
+
+    use crate::upscale::CancelHandle;
+
+    use super::{epx_block, scale_pixels, smooth_block};
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const RED: Rgba<u8> = Rgba([200, 30, 30, 255]);
+
+    // Red below the diagonal, white above it.
+    fn diagonal() -> RgbaImage {
+        RgbaImage::from_fn(4, 4, |x, y| if x <= y { RED } else { WHITE })
+    }
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let flat = RgbaImage::from_pixel(3, 3, RED);
+
+        for factor in 2..=4 {
+            let scaled = scale_pixels(&flat, factor, smooth_block, &CancelHandle::default(), &mut |_| {}).unwrap();
+
+            assert_eq!(scaled.dimensions(), (3 * factor, 3 * factor));
+            assert!(scaled.pixels().all(|pixel| *pixel == RED));
+        }
+    }
+
+    #[test]
+    fn smooth_blends_corners_along_edges() {
+        let scaled = scale_pixels(&diagonal(), 2, smooth_block, &CancelHandle::default(), &mut |_| {}).unwrap();
+
+        // The top right corner of the red pixel at (1, 1) is cut by the edge.
+        let corner = *scaled.get_pixel(3, 2);
+
+        assert!(corner != RED && corner != WHITE, "{:?}", corner);
+        assert_eq!(*scaled.get_pixel(2, 3), RED);
+    }
+
+    #[test]
+    fn epx_fills_corners_along_edges() {
+        let scaled = scale_pixels(&diagonal(), 2, epx_block, &CancelHandle::default(), &mut |_| {}).unwrap();
+
+        assert_eq!(*scaled.get_pixel(3, 2), WHITE);
+        assert_eq!(*scaled.get_pixel(2, 3), RED);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{path::{Path, PathBuf}, thread, time::Duration};
+
+use ::image::imageops::FilterType;
+
+use crate::{error::{Cause, Error}, integrity::Integrity, upscale::{CancelHandle, JobState, Model, ProgressTracker, UpscaleOptions}};
+
+use super::{backend::{Capabilities, ModelScan, UpscaleBackend}, resample::save_image};
+
+// Deterministic backend that plays back scripted progress and writes a plainly resized image.
+// It needs no GPU or upscayl-bin so the queue, UI state and error paths can be tested anywhere.
+pub struct ScriptedBackend {
+    step_delay: Duration
+}
+
+struct Script {
+    name: &'static str,
+    progress: &'static [f32],
+    passes: u32,
+    fails: bool,
+    // Runs out of memory unless the tile size is at most this.
+    max_tile_size: Option<u32>
+}
+
+const SCRIPTS: [Script; 4] = [
+    Script { name: "scripted", progress: &[0.0, 25.0, 50.0, 75.0, 100.0], passes: 1, fails: false, max_tile_size: None },
+    Script { name: "scripted-multipass", progress: &[0.0, 50.0, 100.0, 0.0, 50.0, 100.0], passes: 2, fails: false, max_tile_size: None },
+    Script { name: "scripted-fail", progress: &[0.0, 25.0, 50.0], passes: 1, fails: true, max_tile_size: None },
+    Script { name: "scripted-oom", progress: &[0.0, 25.0, 50.0, 75.0, 100.0], passes: 1, fails: false, max_tile_size: Some(64) },
+];
+
+impl ScriptedBackend {
+    pub fn new() -> Self {
+        Self {
+            step_delay: Duration::from_millis(250)
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_step_delay(step_delay: Duration) -> Self {
+        Self { step_delay }
+    }
+
+    fn script(&self, model: &Model) -> Result<&'static Script, Error> {
+        match SCRIPTS.iter().find(|script| script.name == model.name) {
+            Some(script) => Ok(script),
+            None => Err(
+                Error::FailedToUpscaleImage(None, format!("There is no script named '{}'.", model.name))
+            )
+        }
+    }
+}
+
+impl UpscaleBackend for ScriptedBackend {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            tuning: true,
+            ..Default::default()
+        }
+    }
+
+    fn discover_models(&self, _folders: &[PathBuf]) -> Result<ModelScan, Error> {
+        Ok(
+            SCRIPTS.iter().map(|script| Model {
+                path: PathBuf::new(),
+                folder: PathBuf::new(),
+
+                name: script.name.to_string(),
+                backend: self.name().to_string(),
+                info: None,
+                native_scale: None,
+                size: 0,
+                integrity: Integrity::Unchecked
+            }).collect::<Vec<_>>().into()
+        )
+    }
+
+    fn validate(&self, options: &UpscaleOptions) -> Result<(), Error> {
+        match &options.model {
+            Some(model) => self.script(model).map(|_| ()),
+            None => Err(
+                Error::FailedToUpscaleImage(None, "No model was selected.".to_string())
+            )
+        }
+    }
+
+    fn run(
+        &self,
+        input: &Path,
+        output: &Path,
+        options: &UpscaleOptions,
+        cancel_handle: &CancelHandle,
+        on_progress: &mut dyn FnMut(JobState)
+    ) -> Result<(), Error> {
+        let script = self.script(options.model.as_ref().unwrap())?;
+        let mut tracker = ProgressTracker::new(script.passes);
+
+        let out_of_memory = match script.max_tile_size {
+            Some(max_tile_size) => options.tuning.gpu_id != Some(-1)
+                && (options.tuning.tile_size == 0 || options.tuning.tile_size > max_tile_size),
+            None => false
+        };
+
+        for percent in script.progress {
+            if out_of_memory && *percent > 25.0 {
+                return Err(
+                    Error::UpscaleOutOfMemory(Some("vkAllocateMemory failed (scripted)".into()))
+                );
+            }
+
+            if cancel_handle.is_cancelled() {
+                return Err(Error::UpscaleCancelled(None));
+            }
+
+            thread::sleep(self.step_delay);
+            on_progress(tracker.update(*percent));
+        }
+
+        if script.fails {
+            return Err(
+                Error::FailedToUpscaleImage(None, "The scripted backend was told to fail.".to_string())
+            );
+        }
+
+        let image = match ::image::open(input) {
+            Ok(image) => image,
+            Err(error) => return Err(
+                Error::FailedToUpscaleImage(Some(Cause::source(error)), "Failed to read the input image.".to_string())
+            )
+        };
+
+        let scale = options.scale.max(1) as u32;
+        let resized = image.resize_exact(image.width() * scale, image.height() * scale, FilterType::Nearest);
+
+        save_image(resized, output, options)
+    }
+}
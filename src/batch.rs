@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use eframe::egui::{self, Frame, RichText, Sense, Vec2};
+
+use crate::{image::Image, upscale::UpscaleOptions};
+
+const THUMBNAIL_SIZE: Vec2 = Vec2::new(72.0, 72.0);
+
+// Images opened together. They're upscaled with the shared options unless an item has its own.
+pub struct Batch {
+    pub items: Vec<BatchItem>,
+    // Index of the item being previewed.
+    pub selected: usize,
+    // Folder the images were opened from, if they were.
+    pub folder: Option<PathBuf>
+}
+
+pub struct BatchItem {
+    pub image: Image,
+    pub overrides: Option<UpscaleOptions>
+}
+
+impl Batch {
+    pub fn new(images: Vec<Image>, folder: Option<PathBuf>) -> Self {
+        let mut batch = Self {
+            items: Vec::new(),
+            selected: 0,
+            folder
+        };
+
+        batch.add(images);
+        batch
+    }
+
+    // Adds the images that aren't in the batch yet.
+    pub fn add(&mut self, images: Vec<Image>) {
+        for image in images {
+            if !self.items.iter().any(|item| item.image.path == image.path) {
+                self.items.push(BatchItem { image, overrides: None });
+            }
+        }
+    }
+
+    // Forgets the thumbnails of every image, once the batch is closed.
+    pub fn forget_images(&self, ctx: &egui::Context) {
+        for item in self.items.iter() {
+            ctx.forget_image(&uri(&item.image));
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.items.remove(index);
+
+        if self.selected > index || self.selected >= self.items.len() {
+            self.selected = self.selected.saturating_sub(1);
+        }
+    }
+
+    pub fn selected_image(&self) -> Option<&Image> {
+        self.items.get(self.selected).map(|item| &item.image)
+    }
+
+    pub fn selected_overrides_mut(&mut self) -> Option<&mut UpscaleOptions> {
+        self.items.get_mut(self.selected).and_then(|item| item.overrides.as_mut())
+    }
+
+    // Every image along with the options it's upscaled with.
+    pub fn jobs(&self, shared: &UpscaleOptions) -> Vec<(Image, UpscaleOptions)> {
+        self.items.iter()
+            .map(|item| (item.image.clone(), item.overrides.clone().unwrap_or_else(|| shared.clone())))
+            .collect()
+    }
+
+    // The thumbnail strip below the preview. Clicking a thumbnail previews it, and the
+    // checkbox gives the previewed image options of its own, starting from the shared ones.
+    pub fn show_strip(&mut self, ui: &mut egui::Ui, shared: &UpscaleOptions) {
+        let mut removed = None;
+
+        ui.horizontal(|ui| {
+            let mut count = format!("{} images", self.items.len());
+
+            if let Some(folder) = &self.folder {
+                count.push_str(&format!(" from '{}'", folder.display()));
+            }
+
+            let label = ui.label(count);
+
+            if self.folder.is_some() {
+                label.on_hover_text("Subfolders are recreated in the output folder.");
+            }
+
+            if let Some(item) = self.items.get_mut(self.selected) {
+                let mut own_options = item.overrides.is_some();
+
+                let checkbox = ui.checkbox(&mut own_options, "Own options")
+                    .on_hover_text("Change the options of the previewed image without touching the others.");
+
+                if checkbox.changed() {
+                    item.overrides = own_options.then(|| shared.clone());
+                }
+            }
+        });
+
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (index, item) in self.items.iter().enumerate() {
+                    let mut frame = Frame::group(ui.style());
+
+                    if index == self.selected {
+                        frame = frame.stroke(ui.visuals().selection.stroke);
+                    }
+
+                    frame.show(ui, |ui| {
+                        ui.vertical(|ui| {
+                            let name = item.image.path.file_name().unwrap_or_default().to_string_lossy();
+
+                            let (rect, thumbnail) = ui.allocate_exact_size(THUMBNAIL_SIZE, Sense::click());
+
+                            // Only the thumbnails scrolled into view are loaded, a big folder would
+                            // otherwise be decoded all at once.
+                            if ui.is_rect_visible(rect) {
+                                egui::Image::from_uri(uri(&item.image))
+                                    .rounding(2.0)
+                                    .paint_at(ui, rect);
+                            }
+
+                            let thumbnail = thumbnail.on_hover_text(name.as_ref());
+
+                            if thumbnail.clicked() {
+                                self.selected = index;
+                            }
+
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").on_hover_text("Remove it from the batch.").clicked() {
+                                    removed = Some(index);
+                                }
+
+                                if item.overrides.is_some() {
+                                    ui.small(RichText::new("✎").color(ui.visuals().warn_fg_color))
+                                        .on_hover_text("Has its own options.");
+                                }
+                            });
+                        });
+                    });
+                }
+            });
+        });
+
+        if let Some(index) = removed {
+            ui.ctx().forget_image(&uri(&self.items[index].image));
+            self.remove(index);
+        }
+    }
+}
+
+fn uri(image: &Image) -> String {
+    format!("file://{}", image.path.to_string_lossy())
+}
\ No newline at end of file
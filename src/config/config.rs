@@ -1,24 +1,31 @@
 use log::debug;
 use serde::{Deserialize, Serialize};
 
-use std::{error::Error, fs};
-use crate::config::structs::{keybinds::KeyBinds, misc::Misc};
+use std::{fs, path::PathBuf};
+use toml_edit::DocumentMut;
+use crate::{config::structs::{keybinds::KeyBinds, misc::Misc, upscale::Upscale, watch::Watch}, error::{Cause, Error}};
 
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
 pub struct Config {
     pub version: i8,
     pub keybinds: KeyBinds,
 
-    pub misc: Misc
+    pub misc: Misc,
+    #[serde(default)]
+    pub upscale: Upscale,
+    #[serde(default)]
+    pub watch: Watch
 }
 
 impl Config {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
+    pub fn new() -> Result<Self, Error> {
         debug!("Finding operating system's configuration local directory...");
         let local_config_dir = match dirs::config_local_dir() {
             Some(dir) => dir,
             None => {
-                return Err("No config path was found for your OS!?".into());
+                return Err(
+                    Error::FailedToLoadConfig(None, "No config path was found for your OS!?".to_string())
+                );
             }
         };
 
@@ -28,7 +35,7 @@ impl Config {
             debug!("Creating config directory for aeternum...");
             if let Err(err) = fs::create_dir_all(&aeternum_config_dir_path) {
                 return Err(
-                    format!("Unable to create config path: {}", err).into()
+                    Error::FailedToLoadConfig(Some(Cause::source(err)), "Unable to create config path.".to_string())
                 );
             };
 
@@ -41,7 +48,7 @@ impl Config {
             debug!("Creating models directory for aeternum...");
             if let Err(err) = fs::create_dir_all(&models_folder) {
                 return Err(
-                    format!("Unable to create models path: {}", err).into()
+                    Error::FailedToLoadConfig(Some(Cause::source(err)), "Unable to create models path.".to_string())
                 );
             };
 
@@ -52,10 +59,19 @@ impl Config {
 
         if toml_config_path.exists() {
             debug!("Reading and applying config file...");
-            let value = fs::read_to_string(&toml_config_path)?;
+            let value = match fs::read_to_string(&toml_config_path) {
+                Ok(value) => value,
+                Err(error) => return Err(
+                    Error::FailedToLoadConfig(Some(Cause::source(error)), "Unable to read the config file.".to_string())
+                )
+            };
 
-            let config = toml::from_str::<Config>(&value)?;
-            return Ok(config);
+            return match toml::from_str::<Config>(&value) {
+                Ok(config) => Ok(config),
+                Err(error) => Err(
+                    Error::FailedToLoadConfig(Some(Cause::source(error)), "The config file is not valid.".to_string())
+                )
+            };
         }
 
         debug!(
@@ -73,12 +89,55 @@ impl Config {
             ),
             Err(error) => {
                 Err(
-                    format!(
-                        "Unable to create toml config at '{}'! Defaulting to default config. Error: {}",
-                        toml_config_path.to_string_lossy().to_string(), error
-                    ).into()
+                    Error::FailedToLoadConfig(
+                        Some(Cause::source(error)),
+                        format!(
+                            "Unable to create toml config at '{}'!",
+                            toml_config_path.to_string_lossy().to_string()
+                        )
+                    )
                 )
             }
         }
     }
+
+    pub fn path() -> Result<PathBuf, Error> {
+        match dirs::config_local_dir() {
+            Some(dir) => Ok(dir.join("cloudy").join("aeternum").join("config.toml")),
+            None => Err(
+                Error::FailedToLoadConfig(None, "No config path was found for your OS!?".to_string())
+            )
+        }
+    }
+
+    // Edits the config file in place so the user's comments and formatting are kept.
+    pub fn update(edit: impl FnOnce(&mut DocumentMut)) -> Result<(), Error> {
+        let toml_config_path = Self::path()?;
+
+        let value = match fs::read_to_string(&toml_config_path) {
+            Ok(value) => value,
+            Err(_) => include_str!("../../assets/config.template.toml").to_string()
+        };
+
+        let mut document = match value.parse::<DocumentMut>() {
+            Ok(document) => document,
+            Err(error) => return Err(
+                Error::FailedToLoadConfig(Some(Cause::source(error)), "The config file is not valid.".to_string())
+            )
+        };
+
+        edit(&mut document);
+
+        debug!("Writing config file to '{}'...", toml_config_path.display());
+
+        match fs::write(&toml_config_path, document.to_string()) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(
+                Error::FailedToSaveConfig(
+                    Some(Cause::source(error)),
+                    format!("Unable to write to '{}'.", toml_config_path.display())
+                )
+            )
+        }
+    }
 }
\ No newline at end of file
@@ -1,2 +1,2 @@
 pub mod config;
-mod structs;
\ No newline at end of file
+pub mod structs;
\ No newline at end of file
@@ -4,16 +4,23 @@ use serde::{Serialize, Deserialize};
 pub struct KeyBinds {
     #[serde(default = "about_box_default")]
     pub about_box: String,
+    #[serde(default = "model_manager_default")]
+    pub model_manager: String,
 }
 
 impl Default for KeyBinds {
     fn default() -> Self {
         Self {
-            about_box: "A".to_string()
+            about_box: "A".to_string(),
+            model_manager: model_manager_default()
         }
     }
 }
 
 fn about_box_default() -> String {
     "A".to_string()
+}
+
+fn model_manager_default() -> String {
+    "M".to_string()
 }
\ No newline at end of file
@@ -3,14 +3,17 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Misc {
     #[serde(default = "enable_default")] 
-    pub enable_custom_folder: bool
+    pub enable_custom_folder: bool,
+    #[serde(default)]
+    pub setup_complete: bool
 }
 
 
 impl Default for Misc {
     fn default() -> Self {
         Self {
-            enable_custom_folder: true
+            enable_custom_folder: true,
+            setup_complete: false
         }
     }
 }
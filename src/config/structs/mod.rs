@@ -1,2 +1,4 @@
 pub mod keybinds;
-pub mod misc;
\ No newline at end of file
+pub mod misc;
+pub mod upscale;
+pub mod watch;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Upscale {
+    #[serde(default = "concurrent_jobs_default")]
+    pub concurrent_jobs: usize,
+    #[serde(default = "ncnn_tools_default")]
+    pub ncnn_tools: Vec<String>,
+    #[serde(default)]
+    pub binary_path: Option<PathBuf>,
+    #[serde(default)]
+    pub models_folders: Vec<PathBuf>,
+    #[serde(default)]
+    pub recursive_models: bool,
+    #[serde(default)]
+    pub favourite_models: Vec<String>,
+    #[serde(default = "watch_models_default")]
+    pub watch_models: bool,
+    #[serde(default)]
+    pub folder_include: Vec<String>,
+    #[serde(default)]
+    pub folder_exclude: Vec<String>,
+
+    #[serde(default)]
+    pub tile_size: u32,
+    #[serde(default = "threads_default")]
+    pub threads: [u32; 3],
+    #[serde(default)]
+    pub tta: bool,
+    #[serde(default)]
+    pub gpu_id: Option<i32>
+}
+
+impl Default for Upscale {
+    fn default() -> Self {
+        Self {
+            concurrent_jobs: concurrent_jobs_default(),
+            ncnn_tools: ncnn_tools_default(),
+            binary_path: None,
+            models_folders: Vec::new(),
+            recursive_models: false,
+            favourite_models: Vec::new(),
+            watch_models: watch_models_default(),
+            folder_include: Vec::new(),
+            folder_exclude: Vec::new(),
+
+            tile_size: 0,
+            threads: threads_default(),
+            tta: false,
+            gpu_id: None
+        }
+    }
+}
+
+fn concurrent_jobs_default() -> usize {
+    1
+}
+
+fn watch_models_default() -> bool {
+    true
+}
+
+fn ncnn_tools_default() -> Vec<String> {
+    vec!["upscayl-bin".to_string()]
+}
+
+fn threads_default() -> [u32; 3] {
+    [1, 2, 2]
+}
\ No newline at end of file
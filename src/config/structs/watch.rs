@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+// The hot folder watch mode and the preset it upscales with.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Watch {
+    #[serde(default)]
+    pub input: Option<PathBuf>,
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    #[serde(default)]
+    pub move_processed: bool,
+    #[serde(default = "stable_seconds_default")]
+    pub stable_seconds: u64,
+
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub scale: Option<i32>,
+    #[serde(default = "format_default")]
+    pub format: String,
+    #[serde(default)]
+    pub compression: i32,
+    #[serde(default)]
+    pub noise: i32,
+    // Left out of presets saved before they were kept, those use the ones from [upscale].
+    #[serde(default)]
+    pub syncgap: Option<i32>,
+    #[serde(default)]
+    pub tile_size: Option<u32>,
+    #[serde(default)]
+    pub threads: Option<[u32; 3]>,
+    #[serde(default)]
+    pub tta: Option<bool>,
+    #[serde(default)]
+    pub gpu_id: Option<i32>
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self {
+            input: None,
+            output: None,
+            move_processed: false,
+            stable_seconds: stable_seconds_default(),
+
+            model: None,
+            scale: None,
+            format: format_default(),
+            compression: 0,
+            noise: 0,
+            syncgap: None,
+            tile_size: None,
+            threads: None,
+            tta: None,
+            gpu_id: None
+        }
+    }
+}
+
+fn stable_seconds_default() -> u64 {
+    2
+}
+
+fn format_default() -> String {
+    "png".to_string()
+}
@@ -1,25 +1,228 @@
 // https://github.com/cloudy-org/roseate/blob/main/src/error.rs
-use std::{fmt::{self, Display, Formatter}, path::PathBuf};
+use std::{error::Error as StdError, fmt::{self, Display, Formatter}, path::PathBuf, sync::Arc};
 
-type AE = Option<String>;
+type AE = Option<Cause>;
+
+// What lead to an error, either the underlying error or a message with more detail.
+#[derive(Debug, Clone)]
+pub enum Cause {
+    Message(String),
+    Source(Arc<dyn StdError + Send + Sync>)
+}
 
 #[derive(Debug, Clone)]
 pub enum Error {
     FileNotFound(AE, PathBuf, String),
     NoFileSelected(AE),
     FailedToUpscaleImage(AE, String),
+    UpscaleCancelled(AE),
+    UpscaleOutOfMemory(AE),
+    VulkanUnavailable(AE),
+    ModelFailedToLoad(AE, String),
+    UnsupportedInputImage(AE, PathBuf),
+    FailedToWriteOutput(AE, PathBuf),
     UpscaylNotInPath(AE),
+    NcnnToolNotFound(AE, String),
+    NcnnToolHasNoModels(AE, String),
     ModelsFolderNotFound(AE, PathBuf),
     NoModels(AE, PathBuf),
+    ModelNotFound(AE, String),
+    FailedToScanModels(AE, PathBuf),
+    OrphanedModelFile(AE, PathBuf),
+    InvalidModelInfo(AE, PathBuf),
+    InvalidManifest(AE, PathBuf),
+    ModelChecksumMismatch(AE, String),
+    FailedToImportModel(AE, PathBuf),
+    FailedToRemoveModel(AE, String),
+    FailedToRenameModel(AE, String),
+    FailedToWatchModels(AE),
     FailedToInitImage(AE, PathBuf, String),
     ImageFormatNotSupported(AE, String),
-    FailedToGetCurrentExecutablePath(AE)
+    NoImagesInFolder(AE, PathBuf),
+    InvalidFolderPattern(AE, String),
+    InvalidWatchPreset(AE, String),
+    ProcessedRecordFailed(AE, PathBuf),
+    FailedToMoveProcessed(AE, PathBuf),
+    FailedToGetCurrentExecutablePath(AE),
+    FailedToLoadConfig(AE, String),
+    FailedToSaveConfig(AE, String)
+}
+
+impl Cause {
+    pub fn source(error: impl StdError + Send + Sync + 'static) -> Self {
+        Cause::Source(Arc::new(error))
+    }
+}
+
+impl From<String> for Cause {
+    fn from(message: String) -> Self {
+        Cause::Message(message)
+    }
+}
+
+impl From<&str> for Cause {
+    fn from(message: &str) -> Self {
+        Cause::Message(message.to_string())
+    }
+}
+
+impl Display for Cause {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Cause::Message(message) => write!(f, "{}", message),
+            Cause::Source(error) => write!(f, "{}", error)
+        }
+    }
 }
 
 impl Error {
     pub fn message(&self) -> String {
         format!("{}", self)
     }
+
+    pub fn cause(&self) -> Option<&Cause> {
+        match self {
+            Error::FileNotFound(cause, _, _) => cause.as_ref(),
+            Error::NoFileSelected(cause) => cause.as_ref(),
+            Error::FailedToUpscaleImage(cause, _) => cause.as_ref(),
+            Error::UpscaleCancelled(cause) => cause.as_ref(),
+            Error::UpscaleOutOfMemory(cause) => cause.as_ref(),
+            Error::VulkanUnavailable(cause) => cause.as_ref(),
+            Error::ModelFailedToLoad(cause, _) => cause.as_ref(),
+            Error::UnsupportedInputImage(cause, _) => cause.as_ref(),
+            Error::FailedToWriteOutput(cause, _) => cause.as_ref(),
+            Error::UpscaylNotInPath(cause) => cause.as_ref(),
+            Error::NcnnToolNotFound(cause, _) => cause.as_ref(),
+            Error::NcnnToolHasNoModels(cause, _) => cause.as_ref(),
+            Error::ModelsFolderNotFound(cause, _) => cause.as_ref(),
+            Error::NoModels(cause, _) => cause.as_ref(),
+            Error::ModelNotFound(cause, _) => cause.as_ref(),
+            Error::FailedToScanModels(cause, _) => cause.as_ref(),
+            Error::OrphanedModelFile(cause, _) => cause.as_ref(),
+            Error::InvalidModelInfo(cause, _) => cause.as_ref(),
+            Error::InvalidManifest(cause, _) => cause.as_ref(),
+            Error::ModelChecksumMismatch(cause, _) => cause.as_ref(),
+            Error::FailedToImportModel(cause, _) => cause.as_ref(),
+            Error::FailedToRemoveModel(cause, _) => cause.as_ref(),
+            Error::FailedToRenameModel(cause, _) => cause.as_ref(),
+            Error::FailedToWatchModels(cause) => cause.as_ref(),
+            Error::FailedToInitImage(cause, _, _) => cause.as_ref(),
+            Error::ImageFormatNotSupported(cause, _) => cause.as_ref(),
+            Error::NoImagesInFolder(cause, _) => cause.as_ref(),
+            Error::InvalidFolderPattern(cause, _) => cause.as_ref(),
+            Error::InvalidWatchPreset(cause, _) => cause.as_ref(),
+            Error::ProcessedRecordFailed(cause, _) => cause.as_ref(),
+            Error::FailedToMoveProcessed(cause, _) => cause.as_ref(),
+            Error::FailedToGetCurrentExecutablePath(cause) => cause.as_ref(),
+            Error::FailedToLoadConfig(cause, _) => cause.as_ref(),
+            Error::FailedToSaveConfig(cause, _) => cause.as_ref()
+        }
+    }
+
+    // The cause followed by everything that caused it in turn, empty if there's no cause.
+    pub fn detail(&self) -> String {
+        let mut detail = match self.cause() {
+            Some(cause) => cause.to_string(),
+            None => return String::new()
+        };
+
+        let mut source = self.source().and_then(|source| source.source());
+
+        while let Some(error) = source {
+            detail.push_str(&format!(": {}", error));
+            source = error.source();
+        }
+
+        detail
+    }
+
+    // Stable identifier of the variant, safe to match on in scripts and bug reports.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::FileNotFound(..) => "file-not-found",
+            Error::NoFileSelected(..) => "no-file-selected",
+            Error::FailedToUpscaleImage(..) => "upscale-failed",
+            Error::UpscaleCancelled(..) => "upscale-cancelled",
+            Error::UpscaleOutOfMemory(..) => "out-of-memory",
+            Error::VulkanUnavailable(..) => "vulkan-unavailable",
+            Error::ModelFailedToLoad(..) => "model-failed-to-load",
+            Error::UnsupportedInputImage(..) => "unsupported-input-image",
+            Error::FailedToWriteOutput(..) => "output-not-writable",
+            Error::UpscaylNotInPath(..) => "upscayl-not-found",
+            Error::NcnnToolNotFound(..) => "ncnn-tool-not-found",
+            Error::NcnnToolHasNoModels(..) => "ncnn-tool-no-models",
+            Error::ModelsFolderNotFound(..) => "models-folder-not-found",
+            Error::NoModels(..) => "no-models",
+            Error::ModelNotFound(..) => "model-not-found",
+            Error::FailedToScanModels(..) => "models-scan-failed",
+            Error::OrphanedModelFile(..) => "orphaned-model-file",
+            Error::InvalidModelInfo(..) => "model-info-invalid",
+            Error::InvalidManifest(..) => "manifest-invalid",
+            Error::ModelChecksumMismatch(..) => "model-checksum-mismatch",
+            Error::FailedToImportModel(..) => "model-import-failed",
+            Error::FailedToRemoveModel(..) => "model-remove-failed",
+            Error::FailedToRenameModel(..) => "model-rename-failed",
+            Error::FailedToWatchModels(..) => "models-watch-failed",
+            Error::FailedToInitImage(..) => "image-init-failed",
+            Error::ImageFormatNotSupported(..) => "unsupported-image-format",
+            Error::NoImagesInFolder(..) => "folder-no-images",
+            Error::InvalidFolderPattern(..) => "folder-pattern-invalid",
+            Error::InvalidWatchPreset(..) => "watch-preset-invalid",
+            Error::ProcessedRecordFailed(..) => "processed-record-failed",
+            Error::FailedToMoveProcessed(..) => "processed-move-failed",
+            Error::FailedToGetCurrentExecutablePath(..) => "executable-path-unknown",
+            Error::FailedToLoadConfig(..) => "config-failed-to-load",
+            Error::FailedToSaveConfig(..) => "config-failed-to-save"
+        }
+    }
+
+    // Exit code for the headless mode, following the BSD sysexits.h conventions where one fits.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::FailedToUpscaleImage(..) => 1,
+            Error::ImageFormatNotSupported(..)
+            | Error::UnsupportedInputImage(..)
+            | Error::FailedToInitImage(..)
+            | Error::ModelChecksumMismatch(..) => 65, // EX_DATAERR
+            Error::InvalidFolderPattern(..) => 64, // EX_USAGE
+            Error::FileNotFound(..)
+            | Error::NoFileSelected(..)
+            | Error::NoImagesInFolder(..) => 66, // EX_NOINPUT
+            Error::UpscaylNotInPath(..)
+            | Error::NcnnToolNotFound(..)
+            | Error::VulkanUnavailable(..) => 69, // EX_UNAVAILABLE
+            Error::UpscaleOutOfMemory(..) | Error::FailedToGetCurrentExecutablePath(..) => 71, // EX_OSERR
+            Error::FailedToWriteOutput(..)
+            | Error::FailedToSaveConfig(..)
+            | Error::FailedToImportModel(..)
+            | Error::FailedToRenameModel(..)
+            | Error::ProcessedRecordFailed(..) => 73, // EX_CANTCREAT
+            Error::FailedToRemoveModel(..)
+            | Error::FailedToWatchModels(..)
+            | Error::FailedToMoveProcessed(..) => 74, // EX_IOERR
+            Error::ModelsFolderNotFound(..)
+            | Error::NoModels(..)
+            | Error::ModelNotFound(..)
+            | Error::NcnnToolHasNoModels(..)
+            | Error::FailedToScanModels(..)
+            | Error::OrphanedModelFile(..)
+            | Error::InvalidModelInfo(..)
+            | Error::InvalidManifest(..)
+            | Error::ModelFailedToLoad(..)
+            | Error::InvalidWatchPreset(..)
+            | Error::FailedToLoadConfig(..) => 78, // EX_CONFIG
+            Error::UpscaleCancelled(..) => 130
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.cause() {
+            Some(Cause::Source(error)) => Some(error.as_ref()),
+            _ => None
+        }
+    }
 }
 
 impl Display for Error {
@@ -43,6 +246,37 @@ impl Display for Error {
                 \n\nReason: {}",
                 reason
             ),
+            Error::UpscaleCancelled(_) => write!(
+                f, "The upscale was cancelled."
+            ),
+            Error::UpscaleOutOfMemory(_) => write!(
+                f,
+                "The upscaler ran out of memory. \
+                \n\nFix: Lower the tile size in the advanced options, pick a smaller scale or upscale on the CPU (GPU id -1)."
+            ),
+            Error::VulkanUnavailable(_) => write!(
+                f,
+                "The upscaler couldn't set up Vulkan. \
+                \n\nFix: Install or update the Vulkan drivers for your GPU, or upscale on the CPU (GPU id -1)."
+            ),
+            Error::ModelFailedToLoad(_, model) => write!(
+                f,
+                "The model '{}' failed to load. \
+                \n\nFix: Make sure its .param and .bin files are complete and meant for this upscaler, re-download them if not.",
+                model
+            ),
+            Error::UnsupportedInputImage(_, path) => write!(
+                f,
+                "The upscaler couldn't read '{}'. \
+                \n\nFix: Convert the image to PNG, JPG or WebP and try again.",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            Error::FailedToWriteOutput(_, path) => write!(
+                f,
+                "The upscaled image couldn't be written to '{}'. \
+                \n\nFix: Check the output folder exists, has free space and that you're allowed to write to it.",
+                path.display()
+            ),
             Error::FailedToInitImage(_, path, reason) => write!(
                 f,
                 "Failed to initialize the image ({})! Reason: {}",
@@ -52,18 +286,106 @@ impl Display for Error {
             Error::UpscaylNotInPath(..) => write!(
                 f, "upscayl-bin isn't in your path. Install it: https://github.com/upscayl/upscayl-ncnn"
             ),
+            Error::NcnnToolNotFound(_, binary) => write!(
+                f, "{} isn't in your path.", binary
+            ),
+            Error::NcnnToolHasNoModels(_, binary) => write!(
+                f,
+                "{} was found but none of its models were. \
+                \n\nFix: Copy its models into the custom models folder or add the folder they're in to models_folders in the config.",
+                binary
+            ),
             Error::ModelsFolderNotFound(_, path) => write!(
                 f, "Models folder not found: {}", path.display()
             ),
             Error::NoModels(_, path) => write!(
                 f, "No models found in folder: '{}'", path.display()
             ),
+            Error::ModelNotFound(_, name) => write!(
+                f, "The model '{}' was not found.", name
+            ),
+            Error::FailedToScanModels(_, path) => write!(
+                f, "Failed to look for models in '{}'.", path.display()
+            ),
+            Error::OrphanedModelFile(_, path) => write!(
+                f,
+                "'{}' has no matching {} file next to it, so it was skipped. \
+                \n\nFix: Copy the missing file into '{}' or remove this one.",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                match path.extension().is_some_and(|extension| extension == "bin") {
+                    true => ".param",
+                    false => ".bin"
+                },
+                path.parent().unwrap_or(path).display()
+            ),
+            Error::InvalidModelInfo(_, path) => write!(
+                f,
+                "The model info in '{}' couldn't be read, the model is listed without it. \
+                \n\nFix: Make sure it's valid TOML, see the README for the keys it can have.",
+                path.display()
+            ),
+            Error::InvalidManifest(_, path) => write!(
+                f,
+                "The manifest '{}' couldn't be read, the models next to it aren't checked. \
+                \n\nFix: Make sure it's valid TOML with a [sha256] table of file names and hashes.",
+                path.display()
+            ),
+            Error::ModelChecksumMismatch(_, model) => write!(
+                f,
+                "The files of the model '{}' don't match the hashes in its manifest, it's likely broken or incompletely downloaded. \
+                \n\nFix: Download the model again, or update manifest.toml if it was changed on purpose.",
+                model
+            ),
+            Error::FailedToImportModel(_, path) => write!(
+                f, "Failed to import models from '{}'.", path.display()
+            ),
+            Error::FailedToRemoveModel(_, model) => write!(
+                f, "Failed to remove the model '{}'.", model
+            ),
+            Error::FailedToRenameModel(_, model) => write!(
+                f, "Failed to rename the model '{}'.", model
+            ),
+            Error::FailedToWatchModels(_) => write!(
+                f,
+                "Failed to watch the models folders, models added or removed won't show up until aeternum is restarted. \
+                \n\nFix: Use \"Rescan\" in the model manager, or set `watch_models = false` in the config."
+            ),
             Error::ImageFormatNotSupported(_, image_format) => write!(
                 f, "The image format '{}' is not supported!", image_format
             ),
+            Error::NoImagesInFolder(_, path) => write!(
+                f,
+                "No images to upscale were found in '{}'. \
+                \n\nFix: Only PNG, JPG and WebP images are picked up, check the include and exclude patterns let them through.",
+                path.display()
+            ),
+            Error::InvalidFolderPattern(_, pattern) => write!(
+                f, "'{}' isn't a valid glob pattern.", pattern
+            ),
+            Error::InvalidWatchPreset(_, reason) => write!(
+                f,
+                "The watch preset can't be used. {} \
+                \n\nFix: Pick the options and use \"Save preset\" in the watch window, or edit [watch] in the config.",
+                reason
+            ),
+            Error::ProcessedRecordFailed(_, path) => write!(
+                f,
+                "The record of processed images '{}' couldn't be read or written. \
+                \n\nFix: Check the watched folder can be written to. If the file is broken, fix or delete it (deleting it upscales every image in the folder again).",
+                path.display()
+            ),
+            Error::FailedToMoveProcessed(_, path) => write!(
+                f, "'{}' was upscaled but couldn't be moved into the processed folder.", path.display()
+            ),
             Error::FailedToGetCurrentExecutablePath(_) => write!(
                 f, "Failed to get the current path where aeternum is located."
             ),
+            Error::FailedToLoadConfig(_, reason) => write!(
+                f, "Failed to load aeternum's config file! {}", reason
+            ),
+            Error::FailedToSaveConfig(_, reason) => write!(
+                f, "Failed to save aeternum's config file! {}", reason
+            ),
         }
     }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{env, path::{Path, PathBuf}};
 
 use eframe::egui::{self, ImageSource};
 use rfd::FileDialog;
@@ -34,6 +34,24 @@ pub fn select_image() -> Result<Image, Error> {
     image_or_error
 }
 
+pub fn select_images() -> Result<Vec<Image>, Error> {
+    let image_paths = FileDialog::new()
+        .add_filter("images", &["png", "jpeg", "jpg", "webp"])
+        .pick_files();
+
+    match image_paths {
+        Some(paths) => paths.into_iter().map(Image::from_path).collect(),
+        None => Err(Error::NoFileSelected(None))
+    }
+}
+
+pub fn select_image_folder() -> Result<PathBuf, Error> {
+    match FileDialog::new().set_title("Select a folder of images").pick_folder() {
+        Some(path) => Ok(path),
+        None => Err(Error::NoFileSelected(None))
+    }
+}
+
 pub fn save_folder() -> Result<PathBuf, Error> {
     match FileDialog::new().pick_folder() {
         Some(path) => {
@@ -41,4 +59,141 @@ pub fn save_folder() -> Result<PathBuf, Error> {
         },
         None => Err(Error::NoFileSelected(None))
     }
-}
\ No newline at end of file
+}
+
+pub fn select_model_files() -> Result<Vec<PathBuf>, Error> {
+    let paths = FileDialog::new()
+        .set_title("Select model files")
+        .add_filter("models", &["bin", "param", "onnx", "toml"])
+        .pick_files();
+
+    match paths {
+        Some(paths) => Ok(paths),
+        None => Err(Error::NoFileSelected(None))
+    }
+}
+
+pub fn select_archive() -> Result<PathBuf, Error> {
+    let path = FileDialog::new()
+        .set_title("Select a models archive")
+        .add_filter("archives", &["zip", "gz", "tgz"])
+        .pick_file();
+
+    match path {
+        Some(path) => Ok(path),
+        None => Err(Error::NoFileSelected(None))
+    }
+}
+
+// Where models can be dropped by hand, imported models end up here too.
+pub fn custom_models_folder() -> PathBuf {
+    dirs::config_local_dir().unwrap().join("cloudy").join("aeternum").join("models")
+}
+
+pub fn select_binary() -> Result<PathBuf, Error> {
+    match FileDialog::new().set_title("Select an ncnn upscaler binary").pick_file() {
+        Some(path) => Ok(path),
+        None => Err(Error::NoFileSelected(None))
+    }
+}
+
+// Expands a leading `~` and `$VAR`, `${VAR}` or `%VAR%` environment variables in a path
+// from the config. Variables that aren't set are left as they were written.
+pub fn expand_path(path: &Path) -> PathBuf {
+    let path = path.to_string_lossy();
+    let mut expanded = String::new();
+    let mut chars = path.chars().peekable();
+
+    if path == "~" || path.starts_with("~/") || path.starts_with("~\\") {
+        if let Some(home) = dirs::home_dir() {
+            expanded.push_str(&home.to_string_lossy());
+            chars.next();
+        }
+    }
+
+    while let Some(char) = chars.next() {
+        match char {
+            '$' => {
+                let braced = chars.next_if_eq(&'{').is_some();
+                let mut name = String::new();
+
+                while let Some(next) = chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_') {
+                    name.push(next);
+                }
+
+                let closed = braced && chars.next_if_eq(&'}').is_some();
+
+                match env::var(&name) {
+                    Ok(value) if !name.is_empty() && braced == closed => expanded.push_str(&value),
+                    _ => {
+                        expanded.push('$');
+                        if braced { expanded.push('{'); }
+                        expanded.push_str(&name);
+                        if closed { expanded.push('}'); }
+                    }
+                }
+            },
+            '%' => {
+                let rest: String = chars.clone().collect();
+
+                let value = rest.find('%')
+                    .map(|end| &rest[..end])
+                    .filter(|name| !name.is_empty())
+                    .and_then(|name| env::var(name).ok().map(|value| (name.len(), value)));
+
+                match value {
+                    Some((length, value)) => {
+                        expanded.push_str(&value);
+                        chars.nth(length); // skips the name and closing '%'
+                    },
+                    None => expanded.push('%')
+                }
+            },
+            char => expanded.push(char)
+        }
+    }
+
+    PathBuf::from(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::{Path, PathBuf}};
+
+    use super::expand_path;
+
+    fn expand(path: &str) -> String {
+        expand_path(Path::new(path)).to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn expands_the_home_folder() {
+        let home = dirs::home_dir().unwrap();
+
+        assert_eq!(expand_path(Path::new("~")), home);
+        assert_eq!(expand_path(Path::new("~/models")), PathBuf::from(format!("{}/models", home.display())));
+        assert_eq!(expand("~someone/models"), "~someone/models");
+        assert_eq!(expand("models/~"), "models/~");
+    }
+
+    #[test]
+    fn expands_variables() {
+        env::set_var("AETERNUM_TEST_MODELS", "/srv/models");
+
+        assert_eq!(expand("$AETERNUM_TEST_MODELS/ncnn"), "/srv/models/ncnn");
+        assert_eq!(expand("${AETERNUM_TEST_MODELS}_old"), "/srv/models_old");
+        assert_eq!(expand("%AETERNUM_TEST_MODELS%\\ncnn"), "/srv/models\\ncnn");
+    }
+
+    #[test]
+    fn leaves_unset_and_broken_variables_alone() {
+        env::remove_var("AETERNUM_TEST_UNSET");
+        env::set_var("AETERNUM_TEST_SET", "set");
+
+        assert_eq!(expand("$AETERNUM_TEST_UNSET/ncnn"), "$AETERNUM_TEST_UNSET/ncnn");
+        assert_eq!(expand("${AETERNUM_TEST_UNSET}/ncnn"), "${AETERNUM_TEST_UNSET}/ncnn");
+        assert_eq!(expand("%AETERNUM_TEST_UNSET%/ncnn"), "%AETERNUM_TEST_UNSET%/ncnn");
+        assert_eq!(expand("${AETERNUM_TEST_SET/ncnn"), "${AETERNUM_TEST_SET/ncnn");
+        assert_eq!(expand("100%/models$"), "100%/models$");
+    }
+}
@@ -0,0 +1,321 @@
+use std::{io::Write, path::PathBuf, thread, time::Duration};
+
+use clap::Args;
+
+use crate::{config::config::Config, hot_folder::{self, HotFolder}, image::Image, image_folder::{self, FolderFilter}, upscale::{JobState, OutputExt, Tuning, Upscale, UpscaleOptions}, Error};
+
+#[derive(Args, Debug)]
+pub struct UpscaleArgs {
+    /// Valid path to the image to upscale, or a folder to upscale every image in and below.
+    image: PathBuf,
+
+    /// Glob pattern picking the images to upscale from a folder, can be given more than once.
+    /// Replaces `folder_include` from the config.
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Glob pattern of images or subfolders to leave out of a folder, can be given more than once.
+    /// Replaces `folder_exclude` from the config.
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Name of the model to upscale with (e.g. realesrgan-x4plus). With --watch it can be left
+    /// out to upscale with the preset from the [watch] section of the config instead.
+    #[arg(short, long, required_unless_present = "watch")]
+    model: Option<String>,
+
+    /// Factor to upscale the image by. Defaults to the model's native scale, or 4 if it has none.
+    #[arg(short, long, value_parser = clap::value_parser!(i32).range(1..=32))]
+    scale: Option<i32>,
+
+    /// Compression of the output image.
+    #[arg(short, long, default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..=100))]
+    compression: i32,
+
+    /// Denoise level for waifu2x and Real-CUGAN models (-1 to 3).
+    #[arg(short, long, default_value_t = 0, allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-1..=3))]
+    noise: i32,
+
+    /// Sync gap mode for Real-CUGAN models (0 to 3).
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(i32).range(0..=3))]
+    syncgap: i32,
+
+    /// Tile size, 0 picks one automatically. Lower it if the upscale runs out of memory.
+    #[arg(short, long)]
+    tile_size: Option<u32>,
+
+    /// Threads used for loading, processing and saving (e.g. 1:2:2).
+    #[arg(short = 'j', long, value_parser = parse_threads)]
+    threads: Option<[u32; 3]>,
+
+    /// Enable test-time augmentation, slower but slightly better quality.
+    #[arg(short = 'x', long)]
+    tta: bool,
+
+    /// GPU to upscale on, -1 uses the CPU.
+    #[arg(short, long, allow_negative_numbers = true)]
+    gpu_id: Option<i32>,
+
+    /// Format to save the image as: webp, png, jpg
+    #[arg(short, long, default_value = "png")]
+    format: OutputExt,
+
+    /// Folder to write the upscaled image to. Defaults to the folder of the input image.
+    /// Images from a folder are written into the same subfolders they were in.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Keep watching the folder and upscale images as they're added to it, until stopped with Ctrl+C.
+    /// Without --output they're written to the output folder of the [watch] preset if it's used,
+    /// or an "upscaled" folder inside the watched one.
+    #[arg(short, long)]
+    watch: bool,
+
+    /// Move upscaled originals into a "processed" folder inside the watched folder.
+    #[arg(long, requires = "watch")]
+    move_processed: bool,
+}
+
+// Runs an upscale without ever opening a window, returning the exit code for the process.
+pub fn upscale(args: UpscaleArgs, config: Config) -> i32 {
+    let (upscale, init_result) = Upscale::from_config(&config);
+
+    for error in upscale.unavailable.iter().chain(upscale.warnings.iter()) {
+        eprintln!("warning: {}", error);
+    }
+
+    if let Err(error) = init_result {
+        return fail(error);
+    }
+
+    if !args.image.exists() {
+        return fail(
+            Error::FileNotFound(
+                None,
+                args.image,
+                "That file doesn't exist!".to_string()
+            )
+        );
+    }
+
+    if let Some(output) = &args.output {
+        if !output.is_dir() {
+            return fail(
+                Error::FileNotFound(
+                    None,
+                    output.clone(),
+                    "The output folder must be an existing directory.".to_string()
+                )
+            );
+        }
+    }
+
+    let options = match &args.model {
+        Some(name) => {
+            let model = match upscale.find_model(name) {
+                Some(model) => model,
+                None => {
+                    let available: Vec<String> = upscale.models.iter()
+                        .map(|model| model.name.clone())
+                        .collect();
+
+                    return fail(
+                        Error::ModelNotFound(
+                            Some(format!("Available models: {}", available.join(", ")).into()),
+                            name.clone()
+                        )
+                    );
+                }
+            };
+
+            let native_scale = model.native_scale.map(|native_scale| native_scale as i32);
+            let scale = args.scale.or(native_scale).unwrap_or(4);
+
+            if let Some(native_scale) = native_scale.filter(|native_scale| scale % native_scale != 0) {
+                eprintln!(
+                    "warning: '{}' is a {}x model, a scale of {} is resampled from it and looks softer.",
+                    model.name, native_scale, scale
+                );
+            }
+
+            UpscaleOptions {
+                scale,
+                compression: args.compression,
+                noise: args.noise,
+                syncgap: args.syncgap,
+                tuning: Tuning {
+                    tile_size: args.tile_size.unwrap_or(config.upscale.tile_size),
+                    threads: args.threads.unwrap_or(config.upscale.threads),
+                    tta: args.tta || config.upscale.tta,
+                    gpu_id: args.gpu_id.or(config.upscale.gpu_id)
+                },
+                model: Some(model),
+                output_ext: args.format,
+                output: args.output
+            }
+        },
+        // Only the output folder given here replaces the one of the preset.
+        None => match hot_folder::preset_options(&config.watch, &upscale) {
+            Ok(options) => UpscaleOptions { output: args.output.or(options.output), ..options },
+            Err(error) => return fail(error)
+        }
+    };
+
+    let include = match args.include.is_empty() {
+        true => &config.upscale.folder_include,
+        false => &args.include
+    };
+
+    let exclude = match args.exclude.is_empty() {
+        true => &config.upscale.folder_exclude,
+        false => &args.exclude
+    };
+
+    if args.watch {
+        let hot_folder = FolderFilter::new(include, exclude).and_then(|filter| {
+            HotFolder::new(
+                args.image,
+                options,
+                args.move_processed || config.watch.move_processed,
+                Duration::from_secs(config.watch.stable_seconds),
+                filter
+            )
+        });
+
+        return match hot_folder {
+            Ok(hot_folder) => watch(&upscale, hot_folder),
+            Err(error) => fail(error)
+        };
+    }
+
+    if !args.image.is_dir() {
+        let image = match Image::from_path(args.image) {
+            Ok(image) => image,
+            Err(error) => return fail(error)
+        };
+
+        return match run(&upscale, &image, &options) {
+            Ok(_) => 0,
+            Err(error) => fail(error)
+        };
+    }
+
+    let scan = FolderFilter::new(include, exclude)
+        .and_then(|filter| image_folder::scan(&args.image, &filter, options.output.as_slice()));
+
+    let scan = match scan {
+        Ok(scan) => scan,
+        Err(error) => return fail(error)
+    };
+
+    for warning in scan.warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+
+    // Keeps going past failed images, the exit code is the one of the first failure.
+    let mut exit_code = 0;
+    let count = scan.images.len();
+
+    for (index, image) in scan.images.iter().enumerate() {
+        eprintln!("[{}/{}] {}", index + 1, count, image.path.display());
+
+        if let Err(error) = run(&upscale, image, &options) {
+            let code = fail(error);
+
+            if exit_code == 0 {
+                exit_code = code;
+            }
+        }
+    }
+
+    exit_code
+}
+
+// Upscales images as they show up in the hot folder, only returning if recording one as processed
+// fails since it would be upscaled again and again otherwise.
+fn watch(upscale: &Upscale, mut hot_folder: HotFolder) -> i32 {
+    eprintln!(
+        "Watching '{}' for new images, they're written to '{}'. Stop with Ctrl+C.",
+        hot_folder.input.display(),
+        hot_folder.output().display()
+    );
+
+    loop {
+        let scan = hot_folder.poll();
+
+        for warning in scan.warnings.iter() {
+            eprintln!("warning: {}", warning);
+        }
+
+        for image in scan.images {
+            eprintln!("{}", image.path.display());
+
+            let output = match run(upscale, &image, &hot_folder.options) {
+                Ok(output) => Some(output),
+                Err(error) => {
+                    fail(error);
+                    None
+                }
+            };
+
+            if let Err(error) = hot_folder.finish(&image.path, output) {
+                return fail(error);
+            }
+        }
+
+        thread::sleep(hot_folder::POLL_INTERVAL);
+    }
+}
+
+// Upscales one image, printing its progress to stderr and where it was written to stdout.
+fn run(upscale: &Upscale, image: &Image, options: &UpscaleOptions) -> Result<PathBuf, Error> {
+    let state = upscale.upscale_blocking(image, options, |state| {
+        match state {
+            JobState::Starting => eprintln!("{}", state),
+            JobState::Retrying { .. } => eprintln!("\n{}", state),
+            JobState::Running { .. } => {
+                eprint!("\r{:<60}", state.to_string());
+                let _ = std::io::stderr().flush();
+            },
+            _ => {}
+        }
+    });
+
+    eprintln!();
+
+    match state {
+        JobState::Finished { output, elapsed } => {
+            eprintln!("Successfully upscaled image in {} seconds!", elapsed.as_secs());
+            println!("{}", output.display());
+
+            Ok(output)
+        },
+        JobState::Failed(error) => Err(error),
+        _ => Err(Error::UpscaleCancelled(None))
+    }
+}
+
+fn fail(error: Error) -> i32 {
+    log::debug!("{:?}", error);
+    eprintln!("error[{}]: {}", error.code(), error);
+
+    let detail = error.detail();
+
+    if !detail.is_empty() {
+        eprintln!("\ncaused by: {}", detail);
+    }
+
+    error.exit_code()
+}
+
+fn parse_threads(value: &str) -> Result<[u32; 3], String> {
+    let threads: Vec<u32> = match value.split(':').map(|threads| threads.parse::<u32>()).collect() {
+        Ok(threads) => threads,
+        Err(error) => return Err(error.to_string())
+    };
+
+    match threads.as_slice() {
+        [load, process, save] => Ok([*load, *process, *save]),
+        _ => Err("expected three thread counts as load:process:save".to_string())
+    }
+}
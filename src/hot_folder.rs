@@ -0,0 +1,363 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, str::FromStr, time::{Duration, Instant, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::structs::watch::Watch, error::{Cause, Error}, files, image_folder::{self, FolderFilter, ImageScan}, upscale::{OutputExt, Tuning, Upscale, UpscaleOptions}};
+
+// Kept in the watched folder, lists every image already upscaled so a restart doesn't redo them.
+const RECORD: &str = ".aeternum-processed.toml";
+
+// Folders inside the watched folder for the upscaled images (unless another output folder is
+// set) and the moved originals. Neither is looked through for new images.
+const OUTPUT_FOLDER: &str = "upscaled";
+const PROCESSED_FOLDER: &str = "processed";
+
+// How often the watched folder is looked through.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Default)]
+struct Record {
+    #[serde(default)]
+    files: HashMap<String, Processed>
+}
+
+// An image as it was when it was upscaled, it's only done again once it's changed.
+#[derive(Serialize, Deserialize)]
+struct Processed {
+    size: u64,
+    modified: u64,
+    // None if it couldn't be opened as an image.
+    #[serde(default)]
+    output: Option<PathBuf>
+}
+
+// A new file that's still being written, or was until recently.
+struct Pending {
+    size: u64,
+    modified: u64,
+    since: Instant
+}
+
+// Upscales images as they show up in a folder, see the [watch] section of the config.
+pub struct HotFolder {
+    pub input: PathBuf,
+    pub options: UpscaleOptions,
+
+    move_processed: bool,
+    stable_time: Duration,
+    filter: FolderFilter,
+    record: Record,
+    pending: HashMap<PathBuf, Pending>,
+    // Images handed out by `poll` that haven't been finished yet.
+    in_flight: Vec<PathBuf>,
+    // Images whose upscale failed or was cancelled, by their size and modification time. They're
+    // left out of the record so they're tried again once they change or watching starts over.
+    failed: HashMap<PathBuf, (u64, u64)>,
+    last_poll: Option<Instant>
+}
+
+impl HotFolder {
+    // Without an output folder in `options` the images are written to "upscaled" inside `input`.
+    pub fn new(
+        input: PathBuf,
+        mut options: UpscaleOptions,
+        move_processed: bool,
+        stable_time: Duration,
+        filter: FolderFilter
+    ) -> Result<Self, Error> {
+        if !input.is_dir() {
+            return Err(
+                Error::FileNotFound(None, input, "The folder to watch must be an existing directory.".to_string())
+            );
+        }
+
+        let output = options.output.get_or_insert_with(|| input.join(OUTPUT_FOLDER));
+
+        if let Err(error) = fs::create_dir_all(output.as_path()) {
+            return Err(Error::FailedToWriteOutput(Some(Cause::source(error)), output.clone()));
+        }
+
+        let record_path = input.join(RECORD);
+
+        // A broken record isn't started over, that would upscale everything in the folder again.
+        let record = match fs::read_to_string(&record_path) {
+            Ok(content) => match toml::from_str(&content) {
+                Ok(record) => record,
+                Err(error) => return Err(Error::ProcessedRecordFailed(Some(Cause::source(error)), record_path))
+            },
+            Err(_) => Record::default()
+        };
+
+        Ok(
+            Self {
+                input,
+                options,
+
+                move_processed,
+                stable_time,
+                filter,
+                record,
+                pending: HashMap::new(),
+                in_flight: Vec::new(),
+                failed: HashMap::new(),
+                last_poll: None
+            }
+        )
+    }
+
+    // Where the upscaled images are written to.
+    pub fn output(&self) -> &Path {
+        self.options.output.as_deref().unwrap_or(self.input.as_path())
+    }
+
+    // How many new files are waiting to stop changing.
+    pub fn waiting(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Looks through the folder at most once every `POLL_INTERVAL`, returning the new images
+    // that haven't changed for the stable time. They're handed out once, until `finish`.
+    pub fn poll(&mut self) -> ImageScan {
+        let mut scan = ImageScan { images: Vec::new(), warnings: Vec::new() };
+
+        if self.last_poll.is_some_and(|last_poll| last_poll.elapsed() < POLL_INTERVAL) {
+            return scan;
+        }
+
+        self.last_poll = Some(Instant::now());
+
+        let skip = [self.output().to_path_buf(), self.input.join(PROCESSED_FOLDER)];
+        let paths = image_folder::find(&self.input, &self.filter, &skip);
+
+        // Files deleted before they settled down.
+        self.pending.retain(|path, _| paths.contains(path));
+
+        for path in paths {
+            if self.in_flight.contains(&path) {
+                continue;
+            }
+
+            let (size, modified) = match stamp(&path) {
+                Some(stamp) => stamp,
+                None => continue
+            };
+
+            let processed = self.record.files.get(&self.key(&path))
+                .is_some_and(|processed| processed.size == size && processed.modified == modified);
+
+            if processed || self.failed.get(&path) == Some(&(size, modified)) {
+                continue;
+            }
+
+            let stable = match self.pending.get_mut(&path) {
+                Some(pending) if pending.size == size && pending.modified == modified => {
+                    pending.since.elapsed() >= self.stable_time
+                },
+                Some(pending) => {
+                    *pending = Pending { size, modified, since: Instant::now() };
+                    false
+                },
+                None => {
+                    self.pending.insert(path.clone(), Pending { size, modified, since: Instant::now() });
+                    false
+                }
+            };
+
+            if !stable {
+                continue;
+            }
+
+            self.pending.remove(&path);
+
+            match image_folder::open(&self.input, path.clone()) {
+                Ok(image) => {
+                    self.in_flight.push(path);
+                    scan.images.push(image);
+                },
+                Err(error) => {
+                    // Not tried again until it changes, e.g. a broken export.
+                    scan.warnings.push(error);
+
+                    if let Err(error) = self.note(&path, None) {
+                        scan.warnings.push(error);
+                    }
+                }
+            }
+        }
+
+        scan
+    }
+
+    // Notes an image handed out by `poll` as processed, moving it into the processed folder if
+    // that's asked for. `output` is None if upscaling it failed or was cancelled.
+    pub fn finish(&mut self, path: &Path, output: Option<PathBuf>) -> Result<(), Error> {
+        self.in_flight.retain(|in_flight| in_flight != path);
+
+        if output.is_none() {
+            self.failed.insert(path.to_path_buf(), stamp(path).unwrap_or_default());
+            return Ok(());
+        }
+
+        self.failed.remove(path);
+        self.note(path, output)?;
+
+        if !self.move_processed {
+            return Ok(());
+        }
+
+        let destination = self.input.join(PROCESSED_FOLDER).join(path.strip_prefix(&self.input).unwrap_or(path));
+
+        let result = fs::create_dir_all(destination.parent().unwrap())
+            .and_then(|_| fs::rename(path, &destination));
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error) => Err(Error::FailedToMoveProcessed(Some(Cause::source(error)), path.to_path_buf()))
+        }
+    }
+
+    // Lets an image handed out by `poll` be picked up again, e.g. when its job disappeared.
+    pub fn forget(&mut self, path: &Path) {
+        self.in_flight.retain(|in_flight| in_flight != path);
+    }
+
+    fn note(&mut self, path: &Path, output: Option<PathBuf>) -> Result<(), Error> {
+        let record_path = self.input.join(RECORD);
+        let (size, modified) = stamp(path).unwrap_or_default();
+
+        self.record.files.insert(self.key(path), Processed { size, modified, output });
+
+        let result = toml::to_string(&self.record)
+            .map_err(Cause::source)
+            .and_then(|content| fs::write(&record_path, content).map_err(Cause::source));
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(cause) => Err(Error::ProcessedRecordFailed(Some(cause), record_path))
+        }
+    }
+
+    // Images are noted by their path inside the folder, with forward slashes.
+    fn key(&self, path: &Path) -> String {
+        path.strip_prefix(&self.input).unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+// Builds the options the watch preset from the config asks for, on top of the default ones.
+pub fn preset_options(watch: &Watch, upscale: &Upscale) -> Result<UpscaleOptions, Error> {
+    let model = match &watch.model {
+        Some(name) => match upscale.find_model(name) {
+            Some(model) => model,
+            None => return Err(Error::InvalidWatchPreset(None, format!("The model '{}' wasn't found.", name)))
+        },
+        None => return Err(Error::InvalidWatchPreset(None, "No model is set.".to_string()))
+    };
+
+    let output_ext = match OutputExt::from_str(&watch.format) {
+        Ok(output_ext) => output_ext,
+        Err(_) => return Err(
+            Error::InvalidWatchPreset(None, format!("'{}' isn't a format images can be saved as.", watch.format))
+        )
+    };
+
+    let native_scale = model.native_scale.map(|native_scale| native_scale as i32);
+    let defaults = &upscale.default_options;
+
+    Ok(
+        UpscaleOptions {
+            scale: watch.scale.or(native_scale).unwrap_or(4),
+            compression: watch.compression,
+            noise: watch.noise,
+            syncgap: watch.syncgap.unwrap_or(defaults.syncgap),
+            tuning: Tuning {
+                tile_size: watch.tile_size.unwrap_or(defaults.tuning.tile_size),
+                threads: watch.threads.unwrap_or(defaults.tuning.threads),
+                tta: watch.tta.unwrap_or(defaults.tuning.tta),
+                gpu_id: watch.gpu_id.or(defaults.tuning.gpu_id)
+            },
+            model: Some(model),
+            output_ext,
+            output: watch.output.as_deref().map(files::expand_path)
+        }
+    )
+}
+
+// Size and modification time of a file, None if it's gone.
+fn stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+
+    let modified = metadata.modified().ok()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default();
+
+    Some((metadata.len(), modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::{Path, PathBuf}, time::Duration};
+
+    use crate::{image_folder::FolderFilter, upscale::UpscaleOptions};
+
+    use super::HotFolder;
+
+    // A watched folder with one image in it.
+    fn watched_folder(test: &str) -> (PathBuf, PathBuf) {
+        let folder = env::temp_dir().join(format!("aeternum-test-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let image = folder.join("a.png");
+        ::image::RgbImage::new(4, 4).save(&image).unwrap();
+
+        (folder, image)
+    }
+
+    fn start(folder: &Path) -> HotFolder {
+        HotFolder::new(folder.to_path_buf(), UpscaleOptions::default(), false, Duration::ZERO, FolderFilter::default())
+            .unwrap()
+    }
+
+    // The images handed out once everything had time to settle down.
+    fn settled(hot_folder: &mut HotFolder) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+
+        for _ in 0..2 {
+            hot_folder.last_poll = None;
+            paths.extend(hot_folder.poll().images.into_iter().map(|image| image.path));
+        }
+
+        paths
+    }
+
+    #[test]
+    fn upscaled_images_are_not_done_again() {
+        let (folder, image) = watched_folder("upscaled-images");
+        let mut hot_folder = start(&folder);
+
+        assert_eq!(settled(&mut hot_folder), vec![image.clone()]);
+
+        hot_folder.finish(&image, Some(folder.join("upscaled").join("a.png"))).unwrap();
+
+        assert!(settled(&mut hot_folder).is_empty());
+        assert!(settled(&mut start(&folder)).is_empty());
+    }
+
+    #[test]
+    fn failed_images_are_tried_again_after_a_restart() {
+        let (folder, image) = watched_folder("failed-images");
+        let mut hot_folder = start(&folder);
+
+        assert_eq!(settled(&mut hot_folder), vec![image.clone()]);
+
+        hot_folder.finish(&image, None).unwrap();
+
+        assert!(settled(&mut hot_folder).is_empty());
+        assert_eq!(settled(&mut start(&folder)), vec![image]);
+    }
+}
\ No newline at end of file
@@ -2,12 +2,14 @@ use std::path::PathBuf;
 use eframe::egui;
 use imagesize::ImageSize;
 
-use crate::{upscale::UpscaleOptions, Error};
+use crate::{error::Cause, upscale::UpscaleOptions, Error};
 
 #[derive(Clone)]
 pub struct Image {
     pub path: PathBuf,
-    pub image_size: ImageSize
+    pub image_size: ImageSize,
+    // Folder the image is in relative to the folder it was opened from, empty for single images.
+    pub subfolder: PathBuf
 }
 
 impl Image {
@@ -22,7 +24,7 @@ impl Image {
                         Ok(size) => size,
                         Err(why) => return Err(
                             Error::FailedToInitImage(
-                                Some(why.to_string()), 
+                                Some(Cause::source(why)), 
                                 path.clone(), 
                                 "Failed to get image size.".to_string()
                             )
@@ -31,7 +33,8 @@ impl Image {
 
                     Ok(Self {
                         path,
-                        image_size
+                        image_size,
+                        subfolder: PathBuf::new()
                     })
                 },
                 false => Err(Error::ImageFormatNotSupported(None, extension_string.clone())),
@@ -0,0 +1,217 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::{error::{Cause, Error}, image::Image};
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpeg", "jpg", "webp"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false
+};
+
+// Picks which images of a folder are upscaled. Patterns with a "/" are matched against the path
+// relative to the folder, the others against the file name alone, like in a .gitignore.
+#[derive(Debug, Clone, Default)]
+pub struct FolderFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>
+}
+
+// The images found in a folder, and the ones that were skipped since they couldn't be read.
+pub struct ImageScan {
+    pub images: Vec<Image>,
+    pub warnings: Vec<Error>
+}
+
+impl FolderFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, Error> {
+        Ok(
+            Self {
+                include: patterns(include)?,
+                exclude: patterns(exclude)?
+            }
+        )
+    }
+
+    // Every image is included when no include patterns are set.
+    fn includes(&self, relative: &Path) -> bool {
+        (self.include.is_empty() || matches_any(&self.include, relative)) && !self.excludes(relative)
+    }
+
+    fn excludes(&self, relative: &Path) -> bool {
+        matches_any(&self.exclude, relative)
+    }
+}
+
+// Finds the images in `folder` and all of its subfolders, sorted by path. Each keeps the
+// subfolder it's in so the output can be written into the same layout. Hidden files and
+// folders, symlinked folders and the folders in `skip` (e.g. the output folder) are left out.
+pub fn scan(folder: &Path, filter: &FolderFilter, skip: &[PathBuf]) -> Result<ImageScan, Error> {
+    if !folder.is_dir() {
+        return Err(
+            Error::FileNotFound(None, folder.to_path_buf(), "The folder to upscale doesn't exist.".to_string())
+        );
+    }
+
+    let mut scan = ImageScan { images: Vec::new(), warnings: Vec::new() };
+
+    for path in find(folder, filter, skip) {
+        match open(folder, path) {
+            Ok(image) => scan.images.push(image),
+            Err(error) => scan.warnings.push(error)
+        }
+    }
+
+    if scan.images.is_empty() {
+        return Err(Error::NoImagesInFolder(None, folder.to_path_buf()));
+    }
+
+    Ok(scan)
+}
+
+// The paths of the images `scan` would open, without reading them.
+pub fn find(folder: &Path, filter: &FolderFilter, skip: &[PathBuf]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let skip: Vec<PathBuf> = skip.iter()
+        .filter_map(|skip| skip.canonicalize().ok())
+        .collect();
+
+    collect(folder, folder, filter, &skip, &mut paths);
+    paths.sort();
+
+    paths
+}
+
+// Opens an image found in `folder`, keeping the subfolder it's in.
+pub fn open(folder: &Path, path: PathBuf) -> Result<Image, Error> {
+    let subfolder = path.parent()
+        .and_then(|parent| parent.strip_prefix(folder).ok())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    Image::from_path(path).map(|mut image| {
+        image.subfolder = subfolder;
+        image
+    })
+}
+
+fn collect(root: &Path, folder: &Path, filter: &FolderFilter, skip: &[PathBuf], paths: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(error) => {
+            log::warn!("Failed to look into the folder '{}': {}", folder.display(), error);
+            return;
+        }
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                let skipped = path.canonicalize().is_ok_and(|path| skip.contains(&path));
+
+                if !skipped && !filter.excludes(relative) {
+                    collect(root, &path, filter, skip, paths);
+                }
+            },
+            Ok(_) if is_image(&path) && filter.includes(relative) => paths.push(path),
+            _ => {}
+        }
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn patterns(patterns: &[String]) -> Result<Vec<Pattern>, Error> {
+    patterns.iter().map(|pattern| {
+        Pattern::new(pattern).map_err(
+            |error| Error::InvalidFolderPattern(Some(Cause::source(error)), pattern.clone())
+        )
+    }).collect()
+}
+
+fn matches_any(patterns: &[Pattern], relative: &Path) -> bool {
+    let path = relative.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let file_name = relative.file_name().unwrap_or_default().to_string_lossy();
+
+    patterns.iter().any(|pattern| {
+        match pattern.as_str().contains('/') {
+            true => pattern.matches_with(&path, MATCH_OPTIONS),
+            false => pattern.matches_with(&file_name, MATCH_OPTIONS)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::error::Error;
+
+    use super::FolderFilter;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> FolderFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|pattern| pattern.to_string()).collect::<Vec<_>>();
+
+        FolderFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    #[test]
+    fn name_patterns_match_in_any_folder() {
+        let filter = filter(&["*.png"], &[]);
+
+        assert!(filter.includes(Path::new("a.png")));
+        assert!(filter.includes(Path::new("sprites/walk/A.PNG")));
+        assert!(!filter.includes(Path::new("sprites/a.jpg")));
+    }
+
+    #[test]
+    fn path_patterns_match_from_the_folder() {
+        let filter = filter(&["sprites/*.png", "tiles/**/*.webp"], &[]);
+
+        assert!(filter.includes(Path::new("sprites/a.png")));
+        assert!(!filter.includes(Path::new("sprites/walk/a.png")));
+        assert!(!filter.includes(Path::new("other/sprites/a.png")));
+        assert!(filter.includes(Path::new("tiles/a.webp")));
+        assert!(filter.includes(Path::new("tiles/grass/summer/a.webp")));
+    }
+
+    #[test]
+    fn excludes_win_over_includes() {
+        let everything = filter(&[], &["drafts", "*_old.png"]);
+
+        assert!(everything.includes(Path::new("a.jpg")));
+        assert!(everything.excludes(Path::new("drafts")));
+        assert!(everything.excludes(Path::new("art/drafts")));
+        assert!(!everything.includes(Path::new("a_old.png")));
+
+        let pngs = filter(&["*.png"], &["sprites/*"]);
+
+        assert!(!pngs.includes(Path::new("sprites/a.png")));
+        assert!(pngs.includes(Path::new("sprites/walk/a.png")));
+    }
+
+    #[test]
+    fn invalid_patterns_are_errors() {
+        let result = FolderFilter::new(&["[".to_string()], &[]);
+
+        assert!(matches!(result, Err(Error::InvalidFolderPattern(_, pattern)) if pattern == "["));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,251 @@
+use std::{collections::HashMap, fs::{self, File}, io, path::{Path, PathBuf}, sync::Mutex, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{error::{Cause, Error}, upscale::Model};
+
+// Optional file in a models folder listing the sha256 of the model files in it, e.g.
+// [sha256]
+// "realesrgan-x4plus.bin" = "c8e7d1..."
+const MANIFEST: &str = "manifest.toml";
+
+// Model files that are checked, sidecars aren't since they're meant to be edited.
+const CHECKED_EXTENSIONS: [&str; 3] = ["bin", "param", "onnx"];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Integrity {
+    // No manifest lists the model's files.
+    #[default]
+    Unchecked,
+    Verified,
+    // The files that don't match their hash.
+    Mismatch(Vec<String>)
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    sha256: HashMap<String, String>
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    modified: u64,
+    sha256: String
+}
+
+// Checks models against the manifest of their folder. Hashes are cached across runs and
+// only worked out again once a file's size or modification time changes. The default one
+// keeps its hashes to itself.
+#[derive(Default)]
+pub struct Verifier {
+    cache: Option<PathBuf>,
+    hashes: HashMap<String, CachedHash>,
+    hashes_changed: bool,
+    manifests: HashMap<PathBuf, Option<HashMap<String, String>>>,
+    pub warnings: Vec<Error>
+}
+
+impl Verifier {
+    pub fn load() -> Self {
+        let cache = cache_path();
+
+        let hashes = match cache.as_ref().map(fs::read_to_string) {
+            Some(Ok(content)) => toml::from_str(&content).unwrap_or_else(|error| {
+                log::warn!("The model hash cache is broken, starting over: {}", error);
+                HashMap::new()
+            }),
+            _ => HashMap::new()
+        };
+
+        Self {
+            cache,
+            hashes,
+            hashes_changed: false,
+            manifests: HashMap::new(),
+            warnings: Vec::new()
+        }
+    }
+
+    // Whether the manifest of the model's folder lists any of its files, without hashing them.
+    // Problems with the manifest end up in `warnings`.
+    pub fn lists(&mut self, model: &Model) -> bool {
+        let folder = match model_folder(model) {
+            Some(folder) => folder,
+            None => return false
+        };
+
+        match self.manifest(&folder) {
+            Some(manifest) => model_files(&model.path, &folder).iter().any(|(key, _)| manifest.contains_key(key)),
+            None => false
+        }
+    }
+
+    // The manifest is read again every time since it may have been edited after `lists`.
+    // The verifier is shared by every job, it's only locked to look up and store hashes so
+    // hashing a big model doesn't hold up the others.
+    pub fn verify(verifier: &Mutex<Verifier>, model: &Model) -> Integrity {
+        let folder = match model_folder(model) {
+            Some(folder) => folder,
+            None => return Integrity::Unchecked
+        };
+
+        let manifest = match read_manifest(&folder.join(MANIFEST)) {
+            Ok(Some(manifest)) => manifest,
+            _ => return Integrity::Unchecked
+        };
+
+        let mut checked = false;
+        let mut mismatched = Vec::new();
+
+        for (key, path) in model_files(&model.path, &folder) {
+            let expected = match manifest.get(&key) {
+                Some(expected) => expected,
+                None => continue
+            };
+
+            checked = true;
+
+            match hash(verifier, &path) {
+                Ok(hash) if hash.eq_ignore_ascii_case(expected.trim()) => {},
+                Ok(_) => mismatched.push(key),
+                Err(error) => {
+                    log::warn!("Failed to hash '{}': {}", path.display(), error);
+                    mismatched.push(key);
+                }
+            }
+        }
+
+        match (checked, mismatched.is_empty()) {
+            (false, _) => Integrity::Unchecked,
+            (true, true) => Integrity::Verified,
+            (true, false) => Integrity::Mismatch(mismatched)
+        }
+    }
+
+    // Writes the cache back if anything new was hashed.
+    pub fn save(&mut self) {
+        let path = match &self.cache {
+            Some(path) if self.hashes_changed => path,
+            _ => return
+        };
+
+        let result = toml::to_string(&self.hashes)
+            .map_err(|error| error.to_string())
+            .and_then(|content| {
+                fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| fs::write(path, content))
+                    .map_err(|error| error.to_string())
+            });
+
+        match result {
+            Ok(_) => self.hashes_changed = false,
+            Err(error) => log::warn!("Failed to save the model hash cache to '{}': {}", path.display(), error)
+        }
+    }
+
+    fn manifest(&mut self, folder: &Path) -> Option<&HashMap<String, String>> {
+        if !self.manifests.contains_key(folder) {
+            let path = folder.join(MANIFEST);
+
+            let manifest = match read_manifest(&path) {
+                Ok(manifest) => manifest,
+                Err(cause) => {
+                    self.warnings.push(Error::InvalidManifest(Some(cause), path));
+                    None
+                }
+            };
+
+            self.manifests.insert(folder.to_path_buf(), manifest);
+        }
+
+        self.manifests.get(folder).and_then(Option::as_ref)
+    }
+}
+
+// The lock is let go of while the file is hashed, two jobs may then hash the same file.
+fn hash(verifier: &Mutex<Verifier>, path: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let key = path.to_string_lossy().to_string();
+
+    if let Some(cached) = verifier.lock().unwrap().hashes.get(&key) {
+        if cached.size == metadata.len() && cached.modified == modified {
+            return Ok(cached.sha256.clone());
+        }
+    }
+
+    log::debug!("Hashing '{}'...", path.display());
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    let sha256 = format!("{:x}", hasher.finalize());
+
+    let mut verifier = verifier.lock().unwrap();
+
+    verifier.hashes.insert(key, CachedHash { size: metadata.len(), modified, sha256: sha256.clone() });
+    verifier.hashes_changed = true;
+
+    Ok(sha256)
+}
+
+// None if the folder has no manifest.
+fn read_manifest(path: &Path) -> Result<Option<HashMap<String, String>>, Cause> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path).map_err(Cause::source)?;
+
+    match toml::from_str::<Manifest>(&content) {
+        Ok(manifest) => Ok(Some(manifest.sha256)),
+        Err(error) => Err(Cause::source(error))
+    }
+}
+
+// Built-in models have no files and so no folder.
+fn model_folder(model: &Model) -> Option<PathBuf> {
+    match model.path.parent() {
+        Some(folder) if !model.path.as_os_str().is_empty() => Some(folder.to_path_buf()),
+        _ => None
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|folder| folder.join("cloudy").join("aeternum").join("model_hashes.toml"))
+}
+
+// The files of a model along with how the manifest names them, relative to its folder
+// with forward slashes.
+fn model_files(model_path: &Path, folder: &Path) -> Vec<(String, PathBuf)> {
+    let paths: Vec<PathBuf> = match model_path.is_dir() {
+        true => match fs::read_dir(model_path) {
+            Ok(entries) => entries.filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect(),
+            Err(_) => Vec::new()
+        },
+        false => CHECKED_EXTENSIONS.iter()
+            .map(|extension| model_path.with_extension(extension))
+            .filter(|path| path.is_file())
+            .collect()
+    };
+
+    paths.into_iter().filter_map(|path| {
+        let key = path.strip_prefix(folder).ok()?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some((key, path))
+    }).collect()
+}
\ No newline at end of file
@@ -9,12 +9,14 @@ use eframe::egui::{self, Style};
 use egui_notify::ToastLevel;
 use cirrus_theming::v1::Theme;
 use cirrus_egui::v1::styling::Styling;
-use clap::{arg, command, Parser};
+use clap::{arg, command, Parser, Subcommand};
 use error::Error;
+use headless::UpscaleArgs;
 
 use config::config::Config;
 use notifier::NotifierAPI;
 use upscale::Upscale;
+use windows::setup::SetupScreen;
 
 mod error;
 mod notifier;
@@ -24,10 +26,19 @@ mod windows;
 mod files;
 mod upscale;
 mod config;
+mod headless;
+mod backends;
+mod model_info;
+mod model_files;
+mod integrity;
+mod watcher;
+mod image_folder;
+mod batch;
+mod hot_folder;
 
 #[derive(Parser, Debug)]
 #[clap(author = "Ananas")]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
     /// Valid path to image.
     image: Option<String>,
@@ -35,6 +46,15 @@ struct Args {
     /// Valid themes at the moment: dark, light
     #[arg(short, long)]
     theme: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Upscale an image from the command line without opening a window.
+    Upscale(UpscaleArgs),
 }
 
 fn main() -> eframe::Result {
@@ -42,8 +62,32 @@ fn main() -> eframe::Result {
         env::set_var("RUST_LOG", "WARN");
     }
 
+    // Release builds on Windows have no console, borrow the one they were started from so
+    // headless progress, help and errors are printed somewhere.
+    #[cfg(windows)]
+    if env::args().len() > 1 {
+        attach_console();
+    }
+
     env_logger::init();
 
+    let cli_args = Args::parse();
+
+    if let Some(Commands::Upscale(upscale_args)) = cli_args.command {
+        let config = match Config::new() {
+            Ok(config) => config,
+            Err(error) => {
+                log::warn!(
+                    "{} Defaulting to default config. Error: {}", error, error.detail()
+                );
+
+                Config::default()
+            }
+        };
+
+        std::process::exit(headless::upscale(upscale_args, config));
+    }
+
     let notifier = NotifierAPI::new();
 
     let options = eframe::NativeOptions {
@@ -54,8 +98,6 @@ fn main() -> eframe::Result {
         ..Default::default()
     };
 
-    let cli_args = Args::parse();
-
     let image_path = cli_args.image;
     let theme_string = cli_args.theme;
 
@@ -118,8 +160,7 @@ fn main() -> eframe::Result {
 
             notifier.toasts.lock().unwrap().toast_and_log(
                 format!(
-                    "Error occurred getting aeternum's config file! \
-                    Defaulting to default config. Error: {}", error.to_string().as_str()
+                    "{} Defaulting to default config. Error: {}", error, error.detail()
                 ).into(), 
                 ToastLevel::Error
             ).duration(Some(Duration::from_secs(10)));
@@ -128,27 +169,40 @@ fn main() -> eframe::Result {
         }
     };
 
-    let mut upscale = match Upscale::new() {
-        Ok(upscale) => upscale,
+    let (upscale, init_result) = Upscale::from_config(&config);
+
+    // The setup screen opens when nothing can be upscaled, or on first run when an upscaler is missing.
+    let setup = match init_result {
         Err(error) => {
-            notifier.toasts.lock().unwrap().toast_and_log(
-                error.clone().into(), ToastLevel::Error
-            );
+            log::error!("{} Additional Detail: {}", error, error.detail());
 
-            panic!("{}", error.clone().to_string());
-        }
-    };
+            let mut problems = upscale.unavailable.clone();
+            problems.push(error.clone());
 
-    match upscale.init(config.misc.enable_custom_folder) {
-        Ok(_) => {},
-        Err(error) => {
-            notifier.toasts.lock().unwrap().toast_and_log(
-                error.clone().into(), ToastLevel::Error
-            );
+            Some(SetupScreen::new(config.clone(), problems, Err(error)))
+        },
+        Ok(_) if !upscale.unavailable.is_empty() && !config.misc.setup_complete => {
+            Some(SetupScreen::new(config.clone(), upscale.unavailable.clone(), Ok(())))
+        },
+        Ok(_) => {
+            for error in upscale.unavailable.iter() {
+                notifier.toasts.lock().unwrap().toast_and_log(
+                    format!(
+                        "{} Its models won't be available, the built-in CPU resampling models still are.", error.message()
+                    ).into(),
+                    ToastLevel::Warning
+                ).duration(Some(Duration::from_secs(10)));
+            }
+
+            for warning in upscale.warnings.iter() {
+                notifier.toasts.lock().unwrap()
+                    .toast_and_log(warning.clone().into(), ToastLevel::Warning)
+                    .duration(Some(Duration::from_secs(10)));
+            }
 
-            panic!("{}", error.clone().to_string());
+            None
         }
-    }
+    };
 
     eframe::run_native(
         "Aeternum",
@@ -165,7 +219,22 @@ fn main() -> eframe::Result {
                 .set_all()
                 .apply(&cc.egui_ctx);
 
-            Ok(Box::new(Aeternum::new(image, theme, notifier, upscale, config)))
+            Ok(Box::new(Aeternum::new(image, theme, notifier, upscale, setup, config)))
         }),
     )
+}
+
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when started without a console (e.g. from Explorer), which is fine.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,433 @@
+use std::{fs::{self, File}, io::{self, Read}, path::{Path, PathBuf}};
+
+use flate2::read::GzDecoder;
+
+use crate::{error::{Cause, Error}, files, upscale::Model};
+
+// Files that belong to a model, they're imported, removed and renamed together.
+const MODEL_EXTENSIONS: [&str; 4] = ["bin", "param", "onnx", "toml"];
+
+const NOTHING_FOUND: &str = "No .bin and .param pairs or .onnx files were found.";
+const ALL_INSTALLED: &str = "Every model is already installed.";
+
+// Whether the model lives in the custom folder, only those are changed by the model manager.
+pub fn is_custom(model: &Model) -> bool {
+    model.path.is_file() && model.path.starts_with(files::custom_models_folder())
+}
+
+// Size on disk of everything the model is made of, 0 for built-in models.
+pub fn size(model: &Model) -> u64 {
+    if model.path.is_dir() {
+        return match fs::read_dir(&model.path) {
+            Ok(entries) => entries.filter_map(Result::ok)
+                .filter_map(|entry| entry.metadata().ok())
+                .map(|metadata| metadata.len())
+                .sum(),
+            Err(_) => 0
+        };
+    }
+
+    model_files(&model.path).iter()
+        .filter_map(|path| path.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+// What an import put into the custom folder, and what it left out along with why.
+pub struct Imported {
+    pub models: Vec<String>,
+    pub skipped: Vec<String>
+}
+
+impl Imported {
+    pub fn summary(&self) -> String {
+        let mut summary = format!("Installed {}.", self.models.join(", "));
+
+        if !self.skipped.is_empty() {
+            summary.push_str(&format!(" Skipped {}.", self.skipped.join(", ")));
+        }
+
+        summary
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+
+    name.ends_with(".zip") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+// Copies the picked models into the custom folder, a .toml sidecar next to one comes along.
+pub fn import_files(paths: &[PathBuf]) -> Result<Imported, Error> {
+    let folder = custom_folder()?;
+    let mut skipped = Vec::new();
+    let fail = |cause: Cause| Error::FailedToImportModel(Some(cause), paths.first().cloned().unwrap_or_default());
+    let found = find_models(paths, &mut skipped);
+
+    if found.is_empty() {
+        return Err(fail(nothing_to_import(NOTHING_FOUND, &skipped)));
+    }
+
+    let models = without_clashes(found, &folder, &mut skipped);
+
+    if models.is_empty() {
+        return Err(fail(nothing_to_import(ALL_INSTALLED, &skipped)));
+    }
+
+    for model_path in models.iter() {
+        for path in model_files(model_path) {
+            let destination = folder.join(path.file_name().unwrap());
+
+            if let Err(error) = fs::copy(&path, &destination) {
+                return Err(Error::FailedToImportModel(Some(Cause::source(error)), path));
+            }
+        }
+    }
+
+    Ok(Imported { models: model_names(&models), skipped })
+}
+
+// Installs the models in a .zip or .tar.gz into the custom folder, ignoring how the archive
+// arranges them in folders. Everything is unpacked next to the custom folder first so only
+// complete models end up in it.
+pub fn import_archive(archive_path: &Path) -> Result<Imported, Error> {
+    let folder = custom_folder()?;
+    let staging = folder.join(".aeternum-import");
+
+    let result = fs::remove_dir_all(&staging)
+        .or_else(|error| match error.kind() {
+            io::ErrorKind::NotFound => Ok(()),
+            _ => Err(error)
+        })
+        .and_then(|_| fs::create_dir(&staging))
+        .map_err(Cause::source)
+        .and_then(|_| unpack(archive_path, &staging, &folder));
+
+    if let Err(error) = fs::remove_dir_all(&staging) {
+        log::warn!("Failed to clean up '{}': {}", staging.display(), error);
+    }
+
+    result.map_err(|cause| Error::FailedToImportModel(Some(cause), archive_path.to_path_buf()))
+}
+
+pub fn remove(model: &Model) -> Result<(), Error> {
+    if !is_custom(model) {
+        return Err(
+            Error::FailedToRemoveModel(Some("Only models in the custom folder can be removed.".into()), model.name.clone())
+        );
+    }
+
+    for path in model_files(&model.path) {
+        if let Err(error) = fs::remove_file(&path) {
+            return Err(Error::FailedToRemoveModel(Some(Cause::source(error)), model.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn rename(model: &Model, new_name: &str) -> Result<(), Error> {
+    let fail = |cause: Cause| Err(Error::FailedToRenameModel(Some(cause), model.name.clone()));
+
+    if !is_custom(model) {
+        return fail("Only models in the custom folder can be renamed.".into());
+    }
+
+    if new_name.is_empty() || new_name.starts_with('.') || new_name.contains(['/', '\\']) {
+        return fail(format!("'{}' can't be used as a file name.", new_name).into());
+    }
+
+    // Not `with_extension`, a name like "x4.v2" would lose its ".v2".
+    let renamed = |extension: &str| model.path.with_file_name(format!("{}.{}", new_name, extension));
+
+    if MODEL_EXTENSIONS.iter().any(|extension| renamed(extension).exists()) {
+        return fail(format!("A model called '{}' already exists.", new_name).into());
+    }
+
+    for path in model_files(&model.path) {
+        let renamed = renamed(&path.extension().unwrap_or_default().to_string_lossy());
+
+        if let Err(error) = fs::rename(&path, renamed) {
+            return fail(Cause::source(error));
+        }
+    }
+
+    Ok(())
+}
+
+fn custom_folder() -> Result<PathBuf, Error> {
+    let folder = files::custom_models_folder();
+
+    match fs::create_dir_all(&folder) {
+        Ok(_) => Ok(folder),
+        Err(error) => Err(Error::FailedToImportModel(Some(Cause::source(error)), folder))
+    }
+}
+
+// The model's own file and the ones next to it that share its name.
+fn model_files(model_path: &Path) -> Vec<PathBuf> {
+    MODEL_EXTENSIONS.iter()
+        .map(|extension| model_path.with_extension(extension))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn model_names(models: &[PathBuf]) -> Vec<String> {
+    models.iter()
+        .map(|path| path.file_stem().unwrap_or_default().to_string_lossy().to_string())
+        .collect()
+}
+
+fn unpack(archive_path: &Path, staging: &Path, folder: &Path) -> Result<Imported, Cause> {
+    let file = File::open(archive_path).map_err(Cause::source)?;
+    let mut skipped = Vec::new();
+
+    let name = archive_path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+
+    let extracted = match name.as_str() {
+        name if name.ends_with(".zip") => extract_zip(file, staging, &mut skipped)?,
+        name if name.ends_with(".tar.gz") || name.ends_with(".tgz") => extract_tar_gz(file, staging, &mut skipped)?,
+        _ => return Err("Only .zip, .tar.gz and .tgz archives can be installed.".into())
+    };
+
+    let found = find_models(&extracted, &mut skipped);
+
+    if found.is_empty() {
+        return Err(nothing_to_import(NOTHING_FOUND, &skipped));
+    }
+
+    let models = without_clashes(found, folder, &mut skipped);
+
+    if models.is_empty() {
+        return Err(nothing_to_import(ALL_INSTALLED, &skipped));
+    }
+
+    for model_path in models.iter() {
+        for path in model_files(model_path) {
+            fs::rename(&path, folder.join(path.file_name().unwrap())).map_err(Cause::source)?;
+        }
+    }
+
+    Ok(Imported { models: model_names(&models), skipped })
+}
+
+// Picks the complete models out of `paths`, a .bin or .param alone is found with its pair.
+// Everything that isn't part of one is added to `skipped`.
+fn find_models(paths: &[PathBuf], skipped: &mut Vec<String>) -> Vec<PathBuf> {
+    let mut models: Vec<PathBuf> = Vec::new();
+
+    for path in paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        let (model_path, reason) = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin") => (path.clone(), "no matching .param"),
+            Some("param") => (path.with_extension("bin"), "no matching .bin"),
+            Some("onnx") => (path.clone(), ""),
+            // Sidecars come along with their model.
+            Some("toml") if ["bin", "onnx"].iter().any(|extension| path.with_extension(extension).is_file()) => continue,
+            Some("toml") => (PathBuf::new(), "no model with its name"),
+            _ => (PathBuf::new(), "not a model file")
+        };
+
+        let complete = match model_path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin") => model_path.is_file() && model_path.with_extension("param").is_file(),
+            Some("onnx") => model_path.is_file(),
+            _ => false
+        };
+
+        let skip = format!("{} ({})", file_name, reason);
+
+        match complete {
+            true if !models.contains(&model_path) => models.push(model_path),
+            true => {},
+            false if !skipped.contains(&skip) => skipped.push(skip),
+            false => {}
+        }
+    }
+
+    models
+}
+
+// Leaves out the models that are already in the custom folder and the ones that would replace
+// an installed model with the same name, they're added to `skipped` instead.
+fn without_clashes(models: Vec<PathBuf>, folder: &Path, skipped: &mut Vec<String>) -> Vec<PathBuf> {
+    let canonical_folder = folder.canonicalize().unwrap_or_else(|_| folder.to_path_buf());
+    let mut names = Vec::new();
+
+    models.into_iter()
+        .filter(|model_path| {
+            let file_name = model_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let name = model_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+            let in_folder = model_path.canonicalize()
+                .is_ok_and(|path| path.parent() == Some(canonical_folder.as_path()));
+
+            let reason = match in_folder {
+                true => "already in the custom folder",
+                false if names.contains(&name) => "a second model with this name",
+                false if MODEL_EXTENSIONS.iter().any(|extension| folder.join(format!("{}.{}", name, extension)).exists()) => {
+                    "a model with this name is already installed"
+                },
+                false => {
+                    names.push(name);
+                    return true;
+                }
+            };
+
+            skipped.push(format!("{} ({})", file_name, reason));
+            false
+        })
+        .collect()
+}
+
+fn nothing_to_import(reason: &str, skipped: &[String]) -> Cause {
+    let mut cause = reason.to_string();
+
+    if !skipped.is_empty() {
+        cause.push_str(&format!(" Skipped {}.", skipped.join(", ")));
+    }
+
+    cause.into()
+}
+
+fn extract_zip(file: File, folder: &Path, skipped: &mut Vec<String>) -> Result<Vec<PathBuf>, Cause> {
+    let mut archive = zip::ZipArchive::new(file).map_err(Cause::source)?;
+    let mut extracted = Vec::new();
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(Cause::source)?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        if let Some(path) = entry.enclosed_name() {
+            extract_entry(&mut entry, &path, folder, &mut extracted, skipped).map_err(Cause::source)?;
+        }
+    }
+
+    Ok(extracted)
+}
+
+fn extract_tar_gz(file: File, folder: &Path, skipped: &mut Vec<String>) -> Result<Vec<PathBuf>, Cause> {
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut extracted = Vec::new();
+
+    for entry in archive.entries().map_err(Cause::source)? {
+        let mut entry = entry.map_err(Cause::source)?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path().map_err(Cause::source)?.into_owned();
+
+        extract_entry(&mut entry, &path, folder, &mut extracted, skipped).map_err(Cause::source)?;
+    }
+
+    Ok(extracted)
+}
+
+// Only the file name is kept, so nothing can end up outside the folder. Files that aren't part
+// of a model aren't unpacked at all, macOS' "__MACOSX" and "._" metadata isn't even reported.
+fn extract_entry(
+    entry: &mut impl Read,
+    path: &Path,
+    folder: &Path,
+    extracted: &mut Vec<PathBuf>,
+    skipped: &mut Vec<String>
+) -> io::Result<()> {
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => return Ok(())
+    };
+
+    if file_name.starts_with("._") || path.starts_with("__MACOSX") {
+        return Ok(());
+    }
+
+    let is_model_file = Path::new(&file_name).extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| MODEL_EXTENSIONS.contains(&extension));
+
+    if !is_model_file {
+        skipped.push(format!("{} (not a model file)", file_name));
+        return Ok(());
+    }
+
+    let destination = folder.join(&file_name);
+
+    // Two files with the same name in different folders of the archive, the first one is kept.
+    if extracted.contains(&destination) {
+        skipped.push(format!("{} (a second file with this name)", path.display()));
+        return Ok(());
+    }
+
+    let mut output = File::create(&destination)?;
+
+    io::copy(entry, &mut output)?;
+    extracted.push(destination);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::without_clashes;
+
+    // An empty custom folder and a folder to import from.
+    fn folders(test: &str) -> (PathBuf, PathBuf) {
+        let root = env::temp_dir().join(format!("aeternum-test-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&root);
+
+        let (custom, picked) = (root.join("custom"), root.join("picked"));
+        fs::create_dir_all(&custom).unwrap();
+        fs::create_dir_all(&picked).unwrap();
+
+        (custom, picked)
+    }
+
+    #[test]
+    fn new_models_are_kept() {
+        let (custom, picked) = folders("new-models");
+        fs::write(custom.join("x2.onnx"), "").unwrap();
+
+        let model = picked.join("x4.onnx");
+        let mut skipped = Vec::new();
+
+        assert_eq!(without_clashes(vec![model.clone()], &custom, &mut skipped), vec![model]);
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn models_in_the_custom_folder_are_skipped() {
+        let (custom, _) = folders("in-custom-folder");
+        fs::write(custom.join("x4.onnx"), "").unwrap();
+
+        // The same file through a different path is still the same file.
+        let model = custom.join("..").join("custom").join("x4.onnx");
+        let mut skipped = Vec::new();
+
+        assert!(without_clashes(vec![model], &custom, &mut skipped).is_empty());
+        assert_eq!(skipped, ["x4.onnx (already in the custom folder)"]);
+    }
+
+    #[test]
+    fn name_clashes_are_skipped() {
+        let (custom, picked) = folders("name-clashes");
+        fs::write(custom.join("x4.bin"), "").unwrap();
+        fs::write(custom.join("x4.param"), "").unwrap();
+        fs::create_dir_all(picked.join("other")).unwrap();
+
+        let models = vec![picked.join("x4.onnx"), picked.join("x2.bin"), picked.join("other").join("x2.bin")];
+        let mut skipped = Vec::new();
+
+        assert_eq!(without_clashes(models, &custom, &mut skipped), vec![picked.join("x2.bin")]);
+        assert_eq!(skipped, [
+            "x4.onnx (a model with this name is already installed)",
+            "x2.bin (a second model with this name)"
+        ]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use eframe::egui::{RichText, Ui};
+use serde::Deserialize;
+
+use crate::error::{Cause, Error};
+
+// Optional details about a model, read from a `<model>.toml` file next to it.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct ModelInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub scale: Option<u32>,
+    // What the model is good at, e.g. "anime", "photo" or "art".
+    pub tags: Vec<String>,
+    pub author: Option<String>,
+    pub license: Option<String>,
+    pub source: Option<String>
+}
+
+impl ModelInfo {
+    // The sidecar of `models/x4.bin` is `models/x4.toml`, of `models-cunet/` it's `models-cunet.toml`.
+    pub fn sidecar_path(model_path: &Path) -> Option<PathBuf> {
+        model_path.file_stem()
+            .map(|stem| model_path.with_file_name(format!("{}.toml", stem.to_string_lossy())))
+    }
+
+    // Models without a sidecar have no info, a sidecar that can't be read is an error.
+    pub fn load(model_path: &Path) -> Result<Option<Self>, Error> {
+        let path = match Self::sidecar_path(model_path) {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(None)
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) => return Err(Error::InvalidModelInfo(Some(Cause::source(error)), path))
+        };
+
+        match toml::from_str(&content) {
+            Ok(info) => Ok(Some(info)),
+            Err(error) => Err(Error::InvalidModelInfo(Some(Cause::source(error)), path))
+        }
+    }
+
+    // Shown as the info card under the model picker and when hovering a model in it.
+    pub fn show(&self, ui: &mut Ui) {
+        if let Some(description) = &self.description {
+            ui.label(description);
+        }
+
+        if !self.tags.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for tag in self.tags.iter() {
+                    ui.label(RichText::new(tag).small().weak());
+                }
+            });
+        }
+
+        if let Some(author) = &self.author {
+            ui.small(format!("By {}", author));
+        }
+
+        if let Some(license) = &self.license {
+            ui.small(format!("License: {}", license));
+        }
+
+        if let Some(source) = &self.source {
+            ui.hyperlink_to(RichText::new("Source").small(), source);
+        }
+    }
+}
\ No newline at end of file
@@ -39,8 +39,14 @@ impl ToastsManager {
     }
 
     pub fn toast_and_log(&mut self, message: StringOrError, level: ToastLevel) -> &mut Toast {
+        let code = match &message {
+            StringOrError::Error(error) => format!("[{}] ", error.code()),
+            StringOrError::String(_) => String::new()
+        };
+
         let log_message = format!(
-            "{} Additional Detail: {}",
+            "{}{} Additional Detail: {}",
+            code,
             self.string_or_error_to_string(message.clone()),
             self.string_or_error_full_error_msg(message.clone())
         );
@@ -66,19 +72,7 @@ impl ToastsManager {
 
     fn string_or_error_full_error_msg(&self, string_or_error: StringOrError) -> String {
         match string_or_error {
-            StringOrError::Error(error) => {
-                match error {
-                    Error::FileNotFound(actual_error, _, _) => actual_error.unwrap_or_default(),
-                    Error::NoFileSelected(actual_error) => actual_error.unwrap_or_default(),
-                    Error::FailedToUpscaleImage(actual_error, _) => actual_error.unwrap_or_default(),
-                    Error::UpscaylNotInPath(actual_error) => actual_error.unwrap_or_default(),
-                    Error::FailedToInitImage(actual_error, _, _) => actual_error.unwrap_or_default(),
-                    Error::ImageFormatNotSupported(actual_error, _) => actual_error.unwrap_or_default(),
-                    Error::ModelsFolderNotFound(actual_error, _) => actual_error.unwrap_or_default(),
-                    Error::NoModels(actual_error, _) => actual_error.unwrap_or_default(),
-                    Error::FailedToGetCurrentExecutablePath(actual_error) => actual_error.unwrap_or_default()
-                }
-            },
+            StringOrError::Error(error) => error.detail(),
             StringOrError::String(string) => string,
         }
     }
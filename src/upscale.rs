@@ -1,11 +1,12 @@
-use std::{io::{BufRead, BufReader}, path::PathBuf, process::Stdio, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+use std::{env, fmt::{self, Display, Formatter}, fs, mem, path::{Path, PathBuf}, process::Child, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, TryRecvError}, Arc, Mutex}, thread, time::{Duration, Instant}};
 use egui_notify::ToastLevel;
-use std::process::Command;
-use strum_macros::{EnumIter, Display};
+use strum_macros::{EnumIter, EnumString, Display};
+use toml_edit::{value, Array};
 
-use crate::{error::Error, image::Image, notifier::NotifierAPI};
+use crate::{backends::{backend::{Capabilities, ModelScan, UpscaleBackend}, resample::ResampleBackend, ncnn::{NcnnBackend, NcnnProfile}, scripted::ScriptedBackend}, config::config::Config, error::{Cause, Error}, files, image::Image, integrity::{Integrity, Verifier}, model_files, model_info::ModelInfo, notifier::NotifierAPI, watcher::ModelsWatcher};
 
-#[derive(Clone, PartialEq, EnumIter, Display)]
+#[derive(Debug, Clone, PartialEq, EnumIter, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
 pub enum OutputExt {
     #[strum(to_string = "WebP")]
     WebP,
@@ -17,29 +18,151 @@ pub enum OutputExt {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Model {
-    path: PathBuf,
-    folder: PathBuf,
+    pub path: PathBuf,
+    pub folder: PathBuf,
 
-    pub name: String
+    pub name: String,
+    pub backend: String,
+    pub info: Option<ModelInfo>,
+    // Factor the model upscales by on its own, other scales are resampled. None if any scale works.
+    pub native_scale: Option<u32>,
+    // Bytes its files take up, worked out once when the models are scanned.
+    pub size: u64,
+    pub integrity: Integrity
+}
+
+impl Model {
+    pub fn display_name(&self) -> &str {
+        match self.info.as_ref().and_then(|info| info.name.as_deref()) {
+            Some(name) => name,
+            None => &self.name
+        }
+    }
+
+    // Models whose files don't match their manifest are refused.
+    pub fn check_integrity(&self) -> Result<(), Error> {
+        match &self.integrity {
+            Integrity::Mismatch(files) => Err(
+                Error::ModelChecksumMismatch(
+                    Some(format!("Mismatched files: {}", files.join(", ")).into()),
+                    self.name.clone()
+                )
+            ),
+            _ => Ok(())
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct UpscaleOptions {
     pub scale: i32,
     pub compression: i32,
+    pub noise: i32,
+    pub syncgap: i32,
+    pub tuning: Tuning,
     pub model: Option<Model>,
     pub output_ext: OutputExt,
     pub output: Option<PathBuf>
 }
 
+// Flags of the ncnn tools that trade speed for memory or quality.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    // 0 lets the tool pick a tile size for the GPU's memory.
+    pub tile_size: u32,
+    // Thread counts for loading, processing and saving.
+    pub threads: [u32; 3],
+    pub tta: bool,
+    // None lets the tool pick, -1 runs on the CPU.
+    pub gpu_id: Option<i32>
+}
+
+// Lifecycle of an upscale job, shared between the worker thread and whoever observes it.
+#[derive(Debug, Clone)]
+pub enum JobState {
+    Queued,
+    Starting,
+    Running { percent: f32, tile: u32, progress: f32, eta: Option<Duration> },
+    Retrying { message: String },
+    Finished { output: PathBuf, elapsed: Duration },
+    Failed(Error),
+    Cancelled
+}
+
+// Lets another thread stop a job, killing the backend's child process if one is running.
+#[derive(Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>
+}
+
+#[derive(Clone)]
+pub struct Job {
+    pub id: usize,
+    pub image: Image,
+    pub options: UpscaleOptions,
+    pub state: JobState,
+    pub history: Vec<String>,
+
+    backend: Arc<dyn UpscaleBackend>,
+    cancel_handle: CancelHandle
+}
+
+pub struct UpscaleQueue {
+    pub concurrency: usize,
+
+    jobs: Arc<Mutex<Vec<Job>>>,
+    workers: Arc<Mutex<usize>>,
+    // Shared by the workers and the background checks so a file is hashed only once.
+    verifier: Arc<Mutex<Verifier>>,
+    next_id: usize
+}
+
 pub struct Upscale {
     pub options: UpscaleOptions,
-    pub upscaling: bool,
+    pub default_options: UpscaleOptions,
     pub models: Vec<Model>,
+    pub queue: UpscaleQueue,
+    pub unavailable: Vec<Error>,
+    pub warnings: Vec<Error>,
 
-    models_folder: PathBuf,
-    cli_path: PathBuf,
-    upscaling_arc: Arc<Mutex<bool>>
+    models_folders: Vec<PathBuf>,
+    config_folders: Vec<PathBuf>,
+    recursive: bool,
+    favourites: Vec<String>,
+    custom_folder_enabled: bool,
+    watch_models: bool,
+    // The models folders as configured, before subfolders are added.
+    watched_folders: Vec<PathBuf>,
+    watcher: Option<ModelsWatcher>,
+    // Wakes the UI once a reload running in the background is done.
+    on_change: Option<Arc<dyn Fn(Duration) + Send + Sync>>,
+    reloading: Option<Receiver<Result<Reload, Error>>>,
+    // Every folder that's scanned, subfolders included, and everything found in them before
+    // shadowing. A reload only scans the folders that changed again.
+    folders: Vec<PathBuf>,
+    candidates: Vec<Model>,
+    scan_warnings: Vec<Error>,
+    // Models a manifest lists that haven't been hashed yet, and the results of the ones being hashed.
+    unverified: Vec<Model>,
+    verified: Option<Receiver<(PathBuf, Integrity)>>,
+    backends: Vec<Arc<dyn UpscaleBackend>>
+}
+
+// The folders a reload scanned again and what it found in them, worked out in the background.
+struct Reload {
+    folders: Vec<PathBuf>,
+    // Models in these folders are replaced by the ones in `scan`.
+    replaced: Vec<PathBuf>,
+    scan: ModelScan
+}
+
+// What reloading the models after the watched folders changed added and removed.
+pub struct ModelChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // Warnings that weren't there before the reload.
+    pub warnings: Vec<Error>
 }
 
 impl Default for UpscaleOptions {
@@ -47,6 +170,9 @@ impl Default for UpscaleOptions {
         Self {
             scale: 4,
             compression: 0,
+            noise: 0,
+            syncgap: 3,
+            tuning: Tuning::default(),
             model: None,
             output_ext: OutputExt::PNG,
             output: None
@@ -54,259 +180,1385 @@ impl Default for UpscaleOptions {
     }
 }
 
-impl Upscale {
-    #[cfg(feature = "package")]
-    pub fn new() -> Result<Self, Error> {
-        use std::env;
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            tile_size: 0,
+            threads: [1, 2, 2],
+            tta: false,
+            gpu_id: None
+        }
+    }
+}
+
+impl From<&Config> for Tuning {
+    fn from(config: &Config) -> Self {
+        Self {
+            tile_size: config.upscale.tile_size,
+            threads: config.upscale.threads,
+            tta: config.upscale.tta,
+            gpu_id: config.upscale.gpu_id
+        }
+    }
+}
+
+impl UpscaleOptions {
+    pub fn output_path(&self, image: &Image) -> PathBuf {
+        // Images opened from a folder are written into the same subfolders of the output folder.
+        let output_folder = match &self.output {
+            Some(path) => path.join(&image.subfolder),
+            None => image.path.parent().unwrap().to_path_buf()
+        };
+
+        output_folder.join(
+            image.create_output(self).file_name().unwrap()
+        )
+    }
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            if let Err(error) = child.kill() {
+                log::warn!("Failed to kill the upscaler process: {}", error);
+            }
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn set_child(&self, child: Child) {
+        *self.child.lock().unwrap() = Some(child);
+
+        // The job may have been cancelled before the child was handed over.
+        if self.is_cancelled() {
+            self.cancel();
+        }
+    }
+
+    pub fn take_child(&self) -> Option<Child> {
+        self.child.lock().unwrap().take()
+    }
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "Queued"),
+            JobState::Starting => write!(f, "Initializing command..."),
+            JobState::Running { percent, tile, progress, eta } => {
+                write!(f, "Processing: {:.2}%", progress * 100.0)?;
+
+                if *tile > 0 {
+                    write!(f, " (pass {} at {:.0}%)", tile + 1, percent)?;
+                }
 
-        let executable_path = match env::current_exe() {
-            Ok(path) => path,
-            Err(error) => return Err(Error::FailedToGetCurrentExecutablePath(Some(error.to_string())))
+                match eta {
+                    Some(eta) => write!(f, ", {} remaining", format_duration(eta)),
+                    None => Ok(())
+                }
+            },
+            JobState::Retrying { message } => write!(f, "{}", message),
+            JobState::Finished { elapsed, .. } => write!(f, "Finished in {} seconds", elapsed.as_secs()),
+            JobState::Failed(_) => write!(f, "Failed"),
+            JobState::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+// Progress is held below this until the job is done, a pass more than expected might still come.
+const RUNNING_PROGRESS_CAP: f32 = 0.99;
+// Share of the bar the expected passes fill, the rest is left for passes nobody saw coming.
+const EXPECTED_PASSES_SHARE: f32 = 0.9;
+
+// Turns the per-pass percentages upscayl-bin prints into overall progress that never goes backwards.
+pub struct ProgressTracker {
+    started: Option<Instant>,
+    passes: u32,
+    tile: u32,
+    last_percent: f32,
+    // Overall progress when the current pass started.
+    pass_start: f32,
+    progress: f32
+}
+
+impl ProgressTracker {
+    pub fn new(passes: u32) -> Self {
+        Self {
+            started: None,
+            passes: passes.max(1),
+            tile: 0,
+            last_percent: 0.0,
+            pass_start: 0.0,
+            progress: 0.0
+        }
+    }
+
+    pub fn update(&mut self, percent: f32) -> JobState {
+        let started = *self.started.get_or_insert_with(Instant::now);
+
+        // upscayl-bin starts again from 0% for every tile pass (e.g. with TTA).
+        if percent < self.last_percent {
+            self.tile += 1;
+            self.pass_start = self.progress;
+        }
+
+        self.last_percent = percent;
+
+        let pass_progress = (percent / 100.0).clamp(0.0, 1.0);
+
+        // Passes past the expected ones each take half of what's left, so the bar keeps moving
+        // forward without reaching the end before the upscaler exits.
+        let progress = match self.tile < self.passes {
+            true => (self.tile as f32 + pass_progress) / self.passes as f32 * EXPECTED_PASSES_SHARE,
+            false => self.pass_start + (RUNNING_PROGRESS_CAP - self.pass_start) * pass_progress / 2.0
         };
-        
-        let tool_path = if cfg!(unix) {
-            executable_path.with_file_name("upscayl-bin")
-        } else {
-            executable_path.with_file_name("upscayl-bin.exe")
+
+        self.progress = self.progress.max(progress);
+
+        let eta = match self.progress {
+            progress if (0.01..1.0).contains(&progress) => {
+                let elapsed = started.elapsed().as_secs_f32();
+
+                Some(Duration::from_secs_f32(elapsed / progress * (1.0 - progress)))
+            },
+            _ => None
         };
 
-        if !tool_path.exists() {
-            return Err(Error::UpscaylNotInPath(Some("upscayl-bin is not with the aeternum executable.".to_string())))
+        JobState::Running {
+            percent,
+            tile: self.tile,
+            progress: self.progress,
+            eta
+        }
+    }
+}
+
+pub fn format_duration(duration: &Duration) -> String {
+    let seconds = duration.as_secs();
+
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        _ => format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
+
+impl JobState {
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobState::Starting | JobState::Running { .. } | JobState::Retrying { .. })
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self, JobState::Finished { .. } | JobState::Failed(_) | JobState::Cancelled)
+    }
+}
+
+impl UpscaleQueue {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            concurrency,
+
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            workers: Arc::new(Mutex::new(0)),
+            verifier: Arc::new(Mutex::new(Verifier::load())),
+            next_id: 0
         }
+    }
+
+    // Returns the id the job was given.
+    pub fn push(&mut self, image: Image, options: UpscaleOptions, backend: Arc<dyn UpscaleBackend>) -> usize {
+        self.jobs.lock().unwrap().push(
+            Job {
+                id: self.next_id,
+                image,
+                options,
+                state: JobState::Queued,
+                history: Vec::new(),
+
+                backend,
+                cancel_handle: CancelHandle::default()
+            }
+        );
+
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    pub fn jobs(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.lock().unwrap().is_empty()
+    }
 
-        let models_folder = executable_path.with_file_name("models");
+    // Fraction of jobs that are no longer waiting or running.
+    pub fn progress(&self) -> f32 {
+        let jobs = self.jobs.lock().unwrap();
 
-        if !models_folder.exists() {
-            return Err(Error::ModelsFolderNotFound(Some("Folder does not exist.".to_string()), models_folder))
+        if jobs.is_empty() {
+            return 0.0;
         }
 
-        return Ok(Self {
-            options: UpscaleOptions::default(),
-            upscaling: false,
-            models: Vec::new(),
+        let done = jobs.iter().filter(|job| job.state.is_done()).count();
 
-            models_folder,
-            cli_path: tool_path,
-            upscaling_arc: Arc::new(false.into())
-        })
+        done as f32 / jobs.len() as f32
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        self.jobs.lock().unwrap().retain(
+            |job| job.id != id || job.state.is_active()
+        );
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.lock().unwrap().retain(|job| !job.state.is_done());
+    }
+
+    pub fn cancel(&mut self, id: usize) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|job| job.id == id) {
+            match job.state {
+                JobState::Queued => job.state = JobState::Cancelled,
+                _ if job.state.is_active() => job.backend.cancel(&job.cancel_handle),
+                _ => {}
+            }
+        }
     }
 
-    #[cfg(not(feature = "package"))] // NOTE: This only works on linux.
-    pub fn new() -> Result<Self, Error> {
-        use which::which;
+    pub fn cancel_running(&mut self) {
+        for job in self.jobs.lock().unwrap().iter() {
+            if job.state.is_active() {
+                job.backend.cancel(&job.cancel_handle);
+            }
+        }
+    }
+
+    // Spawns worker threads until the concurrency limit is hit or every queued job has a worker.
+    pub fn start(&mut self, notifier: &NotifierAPI) {
+        let mut workers = self.workers.lock().unwrap();
 
-        match which("upscayl-bin") {
-            Ok(path) => {
-                let models_folder = PathBuf::from("/usr/lib/upscayl/models");
+        let queued = self.jobs.lock().unwrap().iter()
+            .filter(|job| matches!(job.state, JobState::Queued))
+            .count();
 
-                if !models_folder.exists() {
-                    return Err(Error::ModelsFolderNotFound(Some("Folder doesn't exist".to_string()), models_folder))
+        let mut spawned = 0;
+
+        while *workers < self.concurrency.max(1) && spawned < queued {
+            *workers += 1;
+            spawned += 1;
+
+            let jobs = self.jobs.clone();
+            let workers_arc = self.workers.clone();
+            let verifier = self.verifier.clone();
+            let notifier = notifier.clone();
+
+            thread::spawn(move || Self::worker(jobs, workers_arc, verifier, notifier));
+        }
+    }
+
+    fn worker(jobs: Arc<Mutex<Vec<Job>>>, workers: Arc<Mutex<usize>>, verifier: Arc<Mutex<Verifier>>, notifier: NotifierAPI) {
+        loop {
+            let job = {
+                let mut workers = workers.lock().unwrap();
+                let mut jobs = jobs.lock().unwrap();
+
+                match jobs.iter_mut().find(|job| matches!(job.state, JobState::Queued)) {
+                    Some(job) => {
+                        job.state = JobState::Starting;
+                        job.clone()
+                    },
+                    None => {
+                        *workers -= 1;
+                        return;
+                    }
                 }
+            };
 
-                Ok(Self {
-                    options: UpscaleOptions::default(),
-                    upscaling: false,
-                    models: Vec::new(),
+            let image_name = job.image.path.file_name().unwrap().to_string_lossy().to_string();
 
-                    models_folder,
-                    cli_path: path,
-                    upscaling_arc: Arc::new(false.into())
-                })
-            },
-            Err(err) => Err(Error::UpscaylNotInPath(Some(err.to_string())))
+            let state = run_job(
+                job.backend.as_ref(), &job.image, &job.options, &verifier, &job.cancel_handle, |state| {
+                    if let Some(queued) = jobs.lock().unwrap().iter_mut().find(|queued| queued.id == job.id) {
+                        queued.state = state.clone();
+
+                        if let JobState::Retrying { message } = state {
+                            queued.history.push(message.clone());
+                        }
+                    }
+
+                    if let JobState::Retrying { message } = state {
+                        notifier.toasts.lock().unwrap()
+                            .toast_and_log(format!("'{}': {}", image_name, message).into(), ToastLevel::Warning)
+                            .duration(Some(Duration::from_secs(5)));
+                    }
+                }
+            );
+
+            match state {
+                JobState::Finished { elapsed, .. } => {
+                    notifier.toasts.lock().unwrap()
+                        .toast_and_log(
+                            format!("Successfully upscaled '{}' in {} seconds!", image_name, elapsed.as_secs()).into(),
+                            ToastLevel::Success
+                        )
+                        .duration(Some(Duration::from_secs(10)));
+                },
+                JobState::Cancelled => {
+                    notifier.toasts.lock().unwrap()
+                        .toast_and_log(
+                            format!("Cancelled upscaling '{}'.", image_name).into(),
+                            ToastLevel::Info
+                        )
+                        .duration(Some(Duration::from_secs(5)));
+                },
+                JobState::Failed(error) => {
+                    notifier.toasts.lock().unwrap()
+                        .toast_and_log(error.into(), ToastLevel::Error)
+                        .duration(Some(Duration::from_secs(10)));
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+impl ModelChanges {
+    // None if the same models are still there, e.g. when only a sidecar was edited.
+    pub fn message(&self) -> Option<String> {
+        let mut message = Vec::new();
+
+        if !self.added.is_empty() {
+            message.push(format!("Added {}.", self.added.join(", ")));
+        }
+
+        if !self.removed.is_empty() {
+            message.push(format!("Removed {}.", self.removed.join(", ")));
+        }
+
+        match message.is_empty() {
+            true => None,
+            false => Some(message.join(" "))
+        }
+    }
+}
+
+impl Upscale {
+    // Backends that fail to set up are left out and their errors kept in `unavailable`.
+    pub fn new(config: &Config) -> Self {
+        // NOTE: The scripted backend needs no GPU or upscayl-bin, it's meant for testing.
+        if env::var("AETERNUM_BACKEND").is_ok_and(|backend| backend == "scripted") {
+            return Self::with_backends(vec![Arc::new(ScriptedBackend::new())], Vec::new());
+        }
+
+        let mut backends: Vec<Arc<dyn UpscaleBackend>> = Vec::new();
+        let mut models_folders = Vec::new();
+        let mut unavailable = Vec::new();
+        let mut tools: Vec<(&NcnnProfile, Option<PathBuf>)> = Vec::new();
+
+        if let Some(binary_path) = &config.upscale.binary_path {
+            let binary_path = files::expand_path(binary_path);
+
+            tools.push((NcnnBackend::profile_for_path(&binary_path), Some(binary_path)));
+        }
+
+        for tool in config.upscale.ncnn_tools.iter() {
+            match NcnnBackend::profile(tool) {
+                // A binary picked in the config takes the place of the one in the PATH.
+                Some(profile) if tools.iter().any(|(added, _)| added.name == profile.name) => continue,
+                Some(profile) => tools.push((profile, None)),
+                None => log::warn!("'{}' is not a supported ncnn upscaler, skipping it.", tool)
+            }
+        }
+
+        let mut ncnn_backends = Vec::new();
+
+        for (profile, binary_path) in tools {
+            let backend = match binary_path {
+                Some(binary_path) => NcnnBackend::with_cli_path(profile, binary_path),
+                None => NcnnBackend::new(profile)
+            };
+
+            match backend {
+                Ok(backend) => ncnn_backends.push(backend),
+                Err(error) => unavailable.push(error)
+            }
+        }
+
+        // Every tool looks through the shared folders, but leaves the other tools' own folders alone.
+        for backend in ncnn_backends.iter() {
+            if let Some(folder) = backend.models_folder() {
+                if !models_folders.iter().any(|added: &PathBuf| added == folder) {
+                    models_folders.push(folder.to_path_buf());
+                }
+            }
+        }
+
+        for mut backend in ncnn_backends {
+            let other_folders = models_folders.iter()
+                .filter(|folder| Some(folder.as_path()) != backend.models_folder())
+                .cloned()
+                .collect();
+
+            backend.set_other_folders(other_folders);
+            backends.push(Arc::new(backend));
+        }
+
+        #[cfg(feature = "onnx")]
+        backends.push(Arc::new(crate::backends::onnx::OnnxBackend));
+
+        backends.push(Arc::new(ResampleBackend));
+
+        let mut upscale = Self::with_backends(backends, models_folders);
+        upscale.unavailable = unavailable;
+        upscale.config_folders = config.upscale.models_folders.iter()
+            .map(|folder| files::expand_path(folder))
+            .collect();
+        upscale.recursive = config.upscale.recursive_models;
+        upscale.favourites = config.upscale.favourite_models.clone();
+        upscale.watch_models = config.upscale.watch_models;
+
+        upscale
+    }
+
+    // Sets up everything the config asks for. Init errors are handed back next to the
+    // half set up `Upscale` so the app can still open and explain what's missing.
+    pub fn from_config(config: &Config) -> (Self, Result<(), Error>) {
+        let mut upscale = Self::new(config);
+
+        upscale.queue.concurrency = config.upscale.concurrent_jobs;
+        upscale.default_options.tuning = config.into();
+        upscale.reset_options();
+
+        let result = upscale.init(config.misc.enable_custom_folder);
+
+        (upscale, result)
+    }
+
+    pub fn with_backends(backends: Vec<Arc<dyn UpscaleBackend>>, models_folders: Vec<PathBuf>) -> Self {
+        Self {
+            options: UpscaleOptions::default(),
+            default_options: UpscaleOptions::default(),
+            models: Vec::new(),
+            queue: UpscaleQueue::new(1),
+            unavailable: Vec::new(),
+            warnings: Vec::new(),
+
+            models_folders,
+            config_folders: Vec::new(),
+            recursive: false,
+            favourites: Vec::new(),
+            custom_folder_enabled: false,
+            watch_models: false,
+            watched_folders: Vec::new(),
+            watcher: None,
+            on_change: None,
+            reloading: None,
+            folders: Vec::new(),
+            candidates: Vec::new(),
+            scan_warnings: Vec::new(),
+            unverified: Vec::new(),
+            verified: None,
+            backends
         }
     }
 
+    // Models folders are merged highest priority first: the ones listed in the config,
+    // the custom folder and then the upscalers' own. When two models share a name the
+    // one found first is kept, so a model in a higher priority folder shadows the others.
     pub fn init(&mut self, enabled: bool) -> Result<(), Error> {
+        let mut folders = Vec::new();
+
+        self.custom_folder_enabled = enabled;
+
+        for folder in self.config_folders.iter() {
+            match folder.is_dir() {
+                true => folders.push(folder.clone()),
+                false => log::warn!("The models folder '{}' from the config doesn't exist, skipping it.", folder.display())
+            }
+        }
+
         if enabled {
-            let path: PathBuf = dirs::config_local_dir().unwrap().join("cloudy").join("aeternum").join("models");
+            let path = files::custom_models_folder();
 
             if path.exists() {
-                self.get_models(path);
+                folders.push(path);
             } else {
-                return Err(Error::NoModels(Some("Custom folder doesn't exist.".to_string()), path))
+                return Err(Error::NoModels(Some("Custom folder doesn't exist.".into()), path))
+            }
+        }
+
+        folders.extend(self.models_folders.iter().cloned());
+
+        self.watched_folders = folders.clone();
+
+        if let Some(watcher) = self.watcher.as_mut() {
+            watcher.watch(&self.watched_folders, self.recursive);
+        }
+
+        let folders = scanned_folders(folders, self.recursive);
+
+        // A reload that's still running would be based on what's about to be replaced.
+        self.reloading = None;
+
+        self.models.clear();
+        self.warnings.clear();
+
+        let scan = discover(&self.backends, &folders)?;
+
+        self.folders = folders;
+        self.candidates = scan.models;
+        self.scan_warnings = scan.warnings;
+
+        self.pick_models()
+    }
+
+    // Keeps the first of the models sharing a name, in the order of the folders they're in.
+    fn pick_models(&mut self) -> Result<(), Error> {
+        self.models.clear();
+        self.warnings = self.scan_warnings.clone();
+        self.unverified.clear();
+        self.verified = None;
+
+        // Only finds out which models a manifest lists, they're hashed later on.
+        let mut verifier = Verifier::default();
+        let mut candidates = self.candidates.clone();
+
+        // Shadowing follows the folders, not the backends. The sort is stable, so within a
+        // folder the earlier backend still wins and built-in models come last.
+        candidates.sort_by_key(|model| folder_priority(&self.folders, &model.path));
+
+        for mut model in candidates {
+            if let Some(kept) = self.models.iter().find(|kept| kept.name == model.name) {
+                log::info!(
+                    "The model '{}' in '{}' is shadowed by the one in '{}'.",
+                    model.name,
+                    model.path.display(),
+                    kept.path.display()
+                );
+
+                continue;
+            }
+
+            match ModelInfo::load(&model.path) {
+                Ok(info) => model.info = info,
+                Err(error) => self.warnings.push(error)
+            }
+
+            // The scale written in the sidecar wins over the one worked out by the backend.
+            if let Some(scale) = model.info.as_ref().and_then(|info| info.scale) {
+                model.native_scale = Some(scale);
+            }
+
+            if verifier.lists(&model) {
+                self.unverified.push(model.clone());
             }
+
+            self.models.push(model);
         }
 
-        self.get_models(self.models_folder.clone());
+        self.warnings.extend(verifier.warnings);
+
+        // Favourites come first, otherwise the order models were found in is kept.
+        self.models.sort_by_key(|model| !self.favourites.contains(&model.name));
 
         if self.models.is_empty() {
-            return Err(Error::NoModels(Some("Vector is empty.".to_string()), self.models_folder.clone()))
+            return Err(Error::NoModels(Some("Vector is empty.".into()), self.folders.last().cloned().unwrap_or_default()))
+        }
+
+        Ok(())
+    }
+
+    // Looks through the models folders again, e.g. after models were imported. The selected
+    // model is kept if it's still there.
+    pub fn rescan(&mut self) -> Result<(), Error> {
+        let result = self.init(self.custom_folder_enabled);
+
+        self.options.model = self.options.model.as_ref()
+            .and_then(|model| self.find_model(&model.name));
+
+        result
+    }
+
+    // Hashes the models listed in a manifest on a thread of its own, calling `on_done` from it
+    // once they're all checked. Until `apply_verified` picks them up they stay unchecked.
+    pub fn verify_in_background(&mut self, on_done: impl Fn() + Send + 'static) {
+        if self.unverified.is_empty() {
+            return;
+        }
+
+        let models = mem::take(&mut self.unverified);
+        let verifier = self.queue.verifier.clone();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for model in models {
+                let integrity = Verifier::verify(&verifier, &model);
+
+                // The models were scanned again in the meantime.
+                if sender.send((model.path, integrity)).is_err() {
+                    return;
+                }
+            }
+
+            verifier.lock().unwrap().save();
+            on_done();
+        });
+
+        self.verified = Some(receiver);
+    }
+
+    // Takes over what the background check found out so far, returning the models that don't
+    // match their manifest.
+    pub fn apply_verified(&mut self) -> Vec<Error> {
+        let receiver = match &self.verified {
+            Some(receiver) => receiver,
+            None => return Vec::new()
+        };
+
+        let mut mismatched = Vec::new();
+
+        for (path, integrity) in receiver.try_iter() {
+            for model in self.models.iter_mut().chain(self.options.model.as_mut()) {
+                if model.path == path {
+                    model.integrity = integrity.clone();
+                }
+            }
+
+            let model = self.models.iter().find(|model| model.path == path);
+
+            if let Some(Err(error)) = model.map(Model::check_integrity) {
+                mismatched.push(error);
+            }
+        }
+
+        mismatched
+    }
+
+    // Starts watching the models folders if the config allows it, `on_change` is called from
+    // other threads with how long to wait before `reload_changed` picks the change up.
+    pub fn watch(&mut self, on_change: impl Fn(Duration) + Send + Sync + 'static) -> Result<(), Error> {
+        if !self.watch_models || self.watcher.is_some() {
+            return Ok(());
         }
 
+        let on_change: Arc<dyn Fn(Duration) + Send + Sync> = Arc::new(on_change);
+        let watcher_on_change = on_change.clone();
+
+        let mut watcher = match ModelsWatcher::new(move |delay| watcher_on_change(delay)) {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                // Only tried once, e.g. the inotify watch limit won't go up while the app is open.
+                self.watch_models = false;
+                return Err(error);
+            }
+        };
+
+        watcher.watch(&self.watched_folders, self.recursive);
+
+        self.watcher = Some(watcher);
+        self.on_change = Some(on_change);
+
         Ok(())
     }
 
-    pub fn update(&mut self) {
-        if let Ok(value) = self.upscaling_arc.try_lock() {
-            self.upscaling = value.clone();
+    // Once the watcher saw the models folders change and settle down, the folders the changes
+    // were in are scanned again in the background. What that found is taken over by a later
+    // call, None until then or when there was nothing to reload.
+    pub fn reload_changed(&mut self) -> Option<Result<ModelChanges, Error>> {
+        let result = match self.reloading.as_ref().map(Receiver::try_recv) {
+            Some(Ok(reload)) => {
+                self.reloading = None;
+                Some(reload.and_then(|reload| self.apply_reload(reload)))
+            },
+            Some(Err(TryRecvError::Empty)) => return None,
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.reloading = None;
+                None
+            },
+            None => None
+        };
+
+        // One reload at a time, each one starts from what the one before found.
+        if let Some(paths) = self.watcher.as_ref().and_then(ModelsWatcher::take_change) {
+            self.start_reload(paths);
+        }
+
+        result
+    }
+
+    fn start_reload(&mut self, paths: Vec<PathBuf>) {
+        let backends = self.backends.clone();
+        let watched_folders = self.watched_folders.clone();
+        let recursive = self.recursive;
+        let before = self.folders.clone();
+        let on_change = self.on_change.clone();
+        let (sender, receiver) = mpsc::channel();
+
+        log::debug!("The models folders changed, scanning them again...");
+
+        thread::spawn(move || {
+            // Subfolders may have come or gone along with the models.
+            let folders = scanned_folders(watched_folders, recursive);
+
+            let mut changed: Vec<PathBuf> = folders.iter()
+                .filter(|folder| !before.contains(folder))
+                .cloned()
+                .collect();
+
+            for path in paths.iter() {
+                if let Some(folder) = folders.get(folder_priority(&folders, path)) {
+                    if !changed.contains(folder) {
+                        changed.push(folder.clone());
+                    }
+                }
+            }
+
+            let result = discover(&backends, &changed).map(|mut scan| {
+                // A tool without models in the changed folders can still have some in the others.
+                scan.warnings.retain(|warning| !matches!(warning, Error::NcnnToolHasNoModels(..)));
+
+                let mut replaced = changed;
+                replaced.extend(before.into_iter().filter(|folder| !folders.contains(folder)));
+
+                Reload { folders, replaced, scan }
+            });
+
+            // The models were scanned from scratch in the meantime if nobody's listening.
+            if sender.send(result).is_ok() {
+                if let Some(on_change) = on_change {
+                    on_change(Duration::ZERO);
+                }
+            }
+        });
+
+        self.reloading = Some(receiver);
+    }
+
+    fn apply_reload(&mut self, reload: Reload) -> Result<ModelChanges, Error> {
+        let names = |models: &[Model]| -> Vec<String> {
+            models.iter().map(|model| model.name.clone()).collect()
+        };
+
+        let before = names(&self.models);
+        let warnings_before: Vec<String> = self.warnings.iter().map(ToString::to_string).collect();
+
+        self.folders = reload.folders;
+
+        self.candidates.retain(|model| {
+            !model.path.parent().is_some_and(|folder| reload.replaced.iter().any(|replaced| replaced == folder))
+        });
+
+        self.candidates.extend(reload.scan.models);
+
+        for warning in reload.scan.warnings {
+            if !self.scan_warnings.iter().any(|kept| kept.to_string() == warning.to_string()) {
+                self.scan_warnings.push(warning);
+            }
+        }
+
+        let result = self.pick_models();
+
+        self.options.model = self.options.model.as_ref()
+            .and_then(|model| self.find_model(&model.name));
+
+        result?;
+
+        let after = names(&self.models);
+
+        Ok(
+            ModelChanges {
+                added: after.iter().filter(|name| !before.contains(name)).cloned().collect(),
+                removed: before.into_iter().filter(|name| !after.contains(name)).collect(),
+                warnings: self.warnings.iter()
+                    .filter(|warning| !warnings_before.contains(&warning.to_string()))
+                    .cloned()
+                    .collect()
+            }
+        )
+    }
+
+    pub fn custom_folder_enabled(&self) -> bool {
+        self.custom_folder_enabled
+    }
+
+    pub fn is_favourite(&self, name: &str) -> bool {
+        self.favourites.iter().any(|favourite| favourite == name)
+    }
+
+    // Stars or un-stars a model and saves the favourites to the config.
+    pub fn set_favourite(&mut self, name: &str, favourite: bool) -> Result<(), Error> {
+        self.favourites.retain(|existing| existing != name);
+
+        if favourite {
+            self.favourites.push(name.to_string());
         }
+
+        self.models.sort_by_key(|model| !self.favourites.contains(&model.name));
+
+        let favourites: Array = self.favourites.iter().collect();
+
+        Config::update(|document| {
+            document["upscale"]["favourite_models"] = value(favourites);
+        })
     }
 
     pub fn reset_options(&mut self) {
-        self.options = UpscaleOptions::default();
+        self.options = self.default_options.clone();
+    }
+
+    pub fn find_model(&self, name: &str) -> Option<Model> {
+        self.models.iter().find(|model| model.name == name).cloned()
     }
 
-    fn upscaling_reset(&mut self) {
-        self.upscaling = false;
-        self.upscaling_arc = Arc::new(false.into());
+    pub fn capabilities(&self, model: &Model) -> Capabilities {
+        match self.backend_for(model) {
+            Ok(backend) => backend.capabilities(),
+            Err(_) => Capabilities::default()
+        }
+    }
+
+    pub fn backend_for(&self, model: &Model) -> Result<Arc<dyn UpscaleBackend>, Error> {
+        match self.backends.iter().find(|backend| backend.name() == model.backend) {
+            Some(backend) => Ok(backend.clone()),
+            None => Err(
+                Error::FailedToUpscaleImage(
+                    None,
+                    format!("No backend named '{}' is available for the model '{}'.", model.backend, model.name)
+                )
+            )
+        }
     }
 
+    // Queues the image with the current options and starts working through the queue.
     pub fn upscale(&mut self, image: Image, notifier: &mut NotifierAPI) {
-        self.upscaling_reset();
+        self.upscale_many(vec![image], notifier);
+    }
 
-        let path = &image.path;
+    pub fn upscale_many(&mut self, images: Vec<Image>, notifier: &mut NotifierAPI) {
+        let jobs = images.into_iter()
+            .map(|image| (image, self.options.clone()))
+            .collect();
 
-        let output_folder = match &self.options.output {
-            Some(path) => path.clone(),
-            None => image.path.parent().unwrap().to_path_buf()
+        if let Err(error) = self.upscale_each(jobs, notifier) {
+            notifier.toasts.lock().unwrap()
+                .toast_and_log(error.into(), ToastLevel::Error)
+                .duration(Some(Duration::from_secs(10)));
+        }
+    }
+
+    // Queues every image with options of its own, returning the ids of the jobs. Nothing is
+    // queued if any of them can't be upscaled, e.g. since its model fails its checksum.
+    pub fn upscale_each(&mut self, jobs: Vec<(Image, UpscaleOptions)>, notifier: &mut NotifierAPI) -> Result<Vec<usize>, Error> {
+        let mut backends = Vec::new();
+
+        for (_, options) in jobs.iter() {
+            let model = match options.model.as_ref() {
+                Some(model) => model,
+                None => return Err(Error::FailedToUpscaleImage(None, "No model was selected.".to_string()))
+            };
+
+            backends.push(model.check_integrity().and_then(|_| self.backend_for(model))?);
+        }
+
+        let ids = jobs.into_iter().zip(backends)
+            .map(|((image, options), backend)| self.queue.push(image, options, backend))
+            .collect();
+
+        self.queue.start(notifier);
+
+        Ok(ids)
+    }
+
+    // Upscales on the current thread, used by the headless mode.
+    pub fn upscale_blocking(
+        &self,
+        image: &Image,
+        options: &UpscaleOptions,
+        on_state: impl FnMut(&JobState)
+    ) -> JobState {
+        let backend = match options.model.as_ref().map(|model| self.backend_for(model)) {
+            Some(Ok(backend)) => backend,
+            Some(Err(error)) => return JobState::Failed(error),
+            None => return JobState::Failed(
+                Error::FailedToUpscaleImage(None, "No model was selected.".to_string())
+            )
+        };
+
+        run_job(backend.as_ref(), image, options, &self.queue.verifier, &CancelHandle::default(), on_state)
+    }
+}
+
+// Drives a job through its lifecycle, reporting every state change and returning the final one.
+fn run_job(
+    backend: &dyn UpscaleBackend,
+    image: &Image,
+    options: &UpscaleOptions,
+    verifier: &Mutex<Verifier>,
+    cancel_handle: &CancelHandle,
+    mut on_state: impl FnMut(&JobState)
+) -> JobState {
+    let now = Instant::now();
+    let output = options.output_path(image);
+
+    on_state(&JobState::Starting);
+
+    if let Some(folder) = output.parent().filter(|folder| !folder.as_os_str().is_empty() && !folder.is_dir()) {
+        if let Err(error) = fs::create_dir_all(folder) {
+            let state = JobState::Failed(Error::FailedToWriteOutput(Some(Cause::source(error)), output));
+
+            on_state(&state);
+            return state;
+        }
+    }
+
+    let mut options = options.clone();
+    let mut attempt = 1;
+
+    // Models are only hashed once they're used, a hash worked out before is reused as long as
+    // the file's size and modification time stay the same.
+    if let Some(model) = options.model.as_mut() {
+        model.integrity = Verifier::verify(verifier, model);
+        verifier.lock().unwrap().save();
+    }
+
+    let result = loop {
+        let integrity = match &options.model {
+            Some(model) => model.check_integrity(),
+            None => Ok(())
+        };
+
+        let result = integrity.and_then(|_| backend.validate(&options)).and_then(
+            |_| backend.run(&image.path, &output, &options, cancel_handle, &mut |state| on_state(&state))
+        );
+
+        // Running out of memory is retried with smaller tiles and in the end on the CPU.
+        let tuning = match &result {
+            Err(Error::UpscaleOutOfMemory(_)) if backend.capabilities().tuning && !cancel_handle.is_cancelled() => {
+                retry_tuning(&options.tuning)
+            },
+            _ => None
+        };
+
+        let tuning = match tuning {
+            Some(tuning) => tuning,
+            None => break result
         };
 
-        let out = output_folder.join(
-            image.create_output(&self.options)
+        attempt += 1;
+
+        let message = match tuning.gpu_id {
+            Some(-1) => format!("Ran out of memory, retrying on the CPU (attempt {}).", attempt),
+            _ => format!("Ran out of memory, retrying with a tile size of {} (attempt {}).", tuning.tile_size, attempt)
+        };
+
+        log::debug!("Upscaling '{}': {}", image.path.display(), message);
+
+        on_state(&JobState::Retrying { message });
+        options.tuning = tuning;
+    };
+
+    let state = match result {
+        Ok(_) => JobState::Finished { output, elapsed: now.elapsed() },
+        Err(Error::UpscaleCancelled(_)) => JobState::Cancelled,
+        Err(error) => JobState::Failed(error)
+    };
+
+    on_state(&state);
+
+    state
+}
+
+// Index of the most specific folder `path` is in, models outside of every folder come last.
+fn folder_priority(folders: &[PathBuf], path: &Path) -> usize {
+    folders.iter()
+        .enumerate()
+        .filter(|(_, folder)| path.starts_with(folder))
+        .max_by_key(|(_, folder)| folder.components().count())
+        .map_or(usize::MAX, |(index, _)| index)
+}
+
+// Next tuning to try after running out of memory, None once even the CPU has failed.
+fn retry_tuning(tuning: &Tuning) -> Option<Tuning> {
+    if tuning.gpu_id == Some(-1) {
+        return None;
+    }
+
+    let mut next = tuning.clone();
+
+    next.tile_size = match tuning.tile_size {
+        0 => 256,
+        tile_size => tile_size / 2
+    };
+
+    if next.tile_size < 32 {
+        let cpu_count = thread::available_parallelism().map_or(1, |count| count.get()) as u32;
+
+        next.tile_size = 0;
+        next.gpu_id = Some(-1);
+        next.threads[1] = next.threads[1].min(cpu_count);
+    }
+
+    Some(next)
+}
+
+// Every model the backends find in `folders`. Tools with the same layout look through the
+// same shared folders and find the same problems, those are only kept once.
+fn discover(backends: &[Arc<dyn UpscaleBackend>], folders: &[PathBuf]) -> Result<ModelScan, Error> {
+    let mut found = ModelScan::default();
+
+    for backend in backends.iter() {
+        let scan = backend.discover_models(folders)?;
+
+        for warning in scan.warnings {
+            if !found.warnings.iter().any(|kept| kept.to_string() == warning.to_string()) {
+                found.warnings.push(warning);
+            }
+        }
+
+        found.models.extend(
+            scan.models.into_iter().map(|mut model| {
+                model.size = model_files::size(&model);
+                model
+            })
         );
+    }
+
+    Ok(found)
+}
+
+// The folders that are looked through for models, subfolders included if `recursive`.
+fn scanned_folders(mut folders: Vec<PathBuf>, recursive: bool) -> Vec<PathBuf> {
+    // Subfolders come right after the folder they're in, so they share its priority.
+    if recursive {
+        folders = folders.into_iter().flat_map(|folder| {
+            let mut nested = vec![folder.clone()];
+            subfolders(&folder, &mut nested);
+            nested
+        }).collect();
+    }
+
+    // The same folder can be reached through more than one entry, e.g. a symlinked mount.
+    let mut seen = Vec::new();
+
+    folders.retain(|folder| {
+        let canonical = folder.canonicalize().unwrap_or_else(|_| folder.clone());
+
+        match seen.contains(&canonical) {
+            true => false,
+            false => {
+                seen.push(canonical);
+                true
+            }
+        }
+    });
+
+    folders
+}
 
-        let path = path.clone();
-        let cli_path = self.cli_path.clone();
-        let upscaling_arc = self.upscaling_arc.clone();
-        let mut notifier_arc = notifier.clone();
-        let options = self.options.clone();
+// Adds every folder below `folder`, depth first and sorted by name. Symlinked folders
+// aren't followed so a link back up the tree can't loop forever.
+fn subfolders(folder: &Path, folders: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(error) => {
+            log::warn!("Failed to look into the models folder '{}': {}", folder.display(), error);
+            return;
+        }
+    };
+
+    let mut children: Vec<PathBuf> = entries.filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+
+    children.sort();
+
+    for child in children {
+        folders.push(child.clone());
+        subfolders(&child, folders);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+    use sha2::{Digest, Sha256};
+
+    use crate::{backends::{backend::UpscaleBackend, ncnn::NcnnBackend, scripted::ScriptedBackend}, error::Error, image::Image, notifier::NotifierAPI};
+
+    use super::{folder_priority, run_job, CancelHandle, Job, JobState, ProgressTracker, Upscale, UpscaleOptions, UpscaleQueue, Verifier};
+
+    // Writes a small image into a folder of its own, the output is written next to it.
+    fn test_image(test: &str) -> Image {
+        let folder = env::temp_dir().join(format!("aeternum-test-{}-{}", std::process::id(), test));
+        fs::create_dir_all(&folder).unwrap();
+
+        let path = folder.join("input.png");
+        ::image::RgbImage::new(4, 4).save(&path).unwrap();
+
+        Image::from_path(path).unwrap()
+    }
+
+    fn scripted_options(backend: &ScriptedBackend, script: &str) -> UpscaleOptions {
+        let model = backend.discover_models(&[]).unwrap().models.into_iter()
+            .find(|model| model.name == script)
+            .unwrap();
 
-        let mut upscaling = self.upscaling_arc.lock().unwrap();
-        *upscaling = true;
+        UpscaleOptions {
+            scale: 2,
+            model: Some(model),
+            ..Default::default()
+        }
+    }
 
-        let upscale_stuff = move || {
-            let now = Instant::now();
+    // Runs a job straight away, returning every state it went through.
+    fn run_script(script: &str, test: &str) -> Vec<JobState> {
+        let backend = ScriptedBackend::with_step_delay(Duration::ZERO);
+        let options = scripted_options(&backend, script);
+        let mut states = Vec::new();
 
-            notifier_arc.set_loading(Some("Initializing command...".into()));
+        run_job(&backend, &test_image(test), &options, &Mutex::new(Verifier::default()), &CancelHandle::default(), |state| {
+            states.push(state.clone())
+        });
+
+        states
+    }
 
-            let mut upscale_command = Command::new(cli_path.to_string_lossy().to_string());
+    fn wait_for(queue: &UpscaleQueue, done: impl Fn(&[Job]) -> bool) -> Vec<Job> {
+        let started = Instant::now();
 
-            #[cfg(target_os = "windows")] {
-                use std::os::windows::process::CommandExt;
+        loop {
+            let jobs = queue.jobs();
 
-                upscale_command.creation_flags(0x08000000);
+            if done(&jobs) {
+                return jobs;
             }
 
-            let model = &options.model.unwrap();
-
-            let cmd = upscale_command
-                .args([
-                    "-i",
-                    &path.to_string_lossy(),
-                    "-o",
-                    &out.to_string_lossy(),
-                    "-m",
-                    &model.folder.to_string_lossy(),
-                    "-n",
-                    &model.name,
-                    "-s",
-                    &options.scale.to_string(),
-                    "-c",
-                    &options.compression.to_string()
-                ])
-                .stderr(Stdio::piped()) // why do you output to stderr :woe: ~ Ananas
-                .spawn();
-
-            match cmd {
-                Ok(mut child) => {
-                    if let Some(stderr) = child.stderr.take() {
-                        let reader = BufReader::new(stderr);
-
-                        for line in reader.lines() {
-                            match line {
-                                Ok(output) => {
-                                    let out_bytes = output.as_bytes();
-
-                                    if !out_bytes.is_empty() && out_bytes[0].is_ascii_digit() {
-                                        notifier_arc.set_loading(Some(format!("Processing: {}", output)));
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
-                    }
+            assert!(started.elapsed() < Duration::from_secs(10), "the queue never got there");
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
 
-                    let status = child.wait_with_output();
-
-                    match status {
-                        Ok(status) => {
-                            if status.status.success() {
-                                let upscale_time = now.elapsed().as_secs();
-
-                                notifier_arc.toasts.lock().unwrap()
-                                    .toast_and_log(format!("Successfully upscaled image in {} seconds!", upscale_time).into(), ToastLevel::Success)
-                                    .duration(Some(Duration::from_secs(10)));
-                            } else {
-                                let error = Error::FailedToUpscaleImage(
-                                    None,
-                                    "Process returned as not successful.".to_string()
-                                );
-                                notifier_arc.toasts.lock().unwrap()
-                                    .toast_and_log(error.into(), ToastLevel::Error)
-                                    .duration(Some(Duration::from_secs(10)));
-                            }
-                        },
-                        Err(error) => {
-                            let error = Error::FailedToUpscaleImage(
-                                Some(error.to_string()),
-                                "Failed to wait for process.".to_string()
-                            );
-
-                            notifier_arc.toasts.lock().unwrap()
-                                .toast_and_log(error.into(), ToastLevel::Error)
-                                .duration(Some(Duration::from_secs(10)));
-                        }
-                    }
-                },
-                Err(error) => {
-                    let error = Error::FailedToUpscaleImage(Some(error.to_string()), "Failed to spawn child process.".to_string());
+    fn all_done(jobs: &[Job]) -> bool {
+        jobs.iter().all(|job| job.state.is_done())
+    }
 
-                    notifier_arc.toasts.lock().unwrap()
-                        .toast_and_log(error.into(), ToastLevel::Error)
-                        .duration(Some(Duration::from_secs(10)));
+    #[test]
+    fn reloads_only_scan_the_changed_folders() {
+        let root = env::temp_dir().join(format!("aeternum-test-{}-reload", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let (first, second, tool) = (root.join("first"), root.join("second"), root.join("tool"));
+
+        let add_model = |folder: &Path, name: &str| {
+            fs::create_dir_all(folder).unwrap();
+            fs::write(folder.join(format!("{}.bin", name)), "").unwrap();
+            fs::write(folder.join(format!("{}.param", name)), "").unwrap();
+        };
+
+        add_model(&first, "a");
+        add_model(&second, "b");
+
+        fs::create_dir_all(&tool).unwrap();
+        fs::write(tool.join("upscayl-bin"), "").unwrap();
+
+        let binary = tool.join("upscayl-bin");
+        let backend = NcnnBackend::with_cli_path(NcnnBackend::profile_for_path(&binary), binary).unwrap();
+
+        let mut upscale = Upscale::with_backends(vec![Arc::new(backend)], Vec::new());
+        upscale.config_folders = vec![first.clone(), second.clone()];
+        upscale.init(false).unwrap();
+
+        let mut reload = |paths: Vec<PathBuf>| {
+            upscale.start_reload(paths);
+
+            let started = Instant::now();
+
+            loop {
+                if let Some(changes) = upscale.reload_changed() {
+                    let names: Vec<String> = upscale.models.iter().map(|model| model.name.clone()).collect();
+                    break (changes.unwrap(), names);
                 }
+
+                assert!(started.elapsed() < Duration::from_secs(10), "the reload never finished");
+                thread::sleep(Duration::from_millis(10));
             }
+        };
 
-            notifier_arc.unset_loading();
+        // Only the second folder is seen changing, the model added to the first one isn't picked up.
+        add_model(&first, "c");
+        add_model(&second, "d");
 
-            let mut upscaling = upscaling_arc.lock().unwrap();
-            *upscaling = false;
-        };
+        let (changes, names) = reload(vec![second.join("d.bin")]);
+
+        assert_eq!(changes.added, ["d"]);
+        assert_eq!(names, ["a", "b", "d"]);
+
+        fs::remove_file(second.join("b.bin")).unwrap();
+        fs::remove_file(second.join("b.param")).unwrap();
+
+        let (changes, names) = reload(vec![second.join("b.bin"), second.join("b.param")]);
 
-        thread::spawn(upscale_stuff);
+        assert_eq!(changes.removed, ["b"]);
+        assert_eq!(names, ["a", "d"]);
     }
 
-    fn get_models(&mut self, folder_path: PathBuf) {
-        let glob_bin = folder_path.join("*.bin");
-        let gl = glob_bin.to_string_lossy();
+    #[test]
+    fn models_are_ranked_by_their_folder() {
+        let folders: Vec<PathBuf> = ["/team/models", "/home/me/custom", "/home/me/custom/nested", "/usr/share/tool/models"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
 
-        for entry in glob::glob(&gl).unwrap() {
-            match entry {
-                Ok(entry_path) => {
-                    if format!("{}", entry_path.display()).contains("video") {
-                        continue;
-                    }
+        assert_eq!(folder_priority(&folders, Path::new("/team/models/x4.onnx")), 0);
+        assert_eq!(folder_priority(&folders, Path::new("/usr/share/tool/models/x4.bin")), 3);
+        assert_eq!(folder_priority(&folders, Path::new("/home/me/custom/nested/x4.bin")), 2);
+        assert_eq!(folder_priority(&folders, Path::new("")), usize::MAX);
+    }
 
-                    let param_file = entry_path.with_file_name(
-                        format!("{}.param", entry_path.file_stem().unwrap().to_string_lossy())
-                    );
+    #[test]
+    fn scripted_job_finishes() {
+        let states = run_script("scripted", "finishes");
+
+        match states.last() {
+            Some(JobState::Finished { output, .. }) => {
+                let upscaled = ::image::open(output).unwrap();
+                assert_eq!((upscaled.width(), upscaled.height()), (8, 8));
+            },
+            state => panic!("expected the job to finish, got {:?}", state)
+        }
+    }
 
-                    if param_file.exists() {
-                        self.models.push(
-                            Model {
-                                path: entry_path.clone(),
-                                folder: folder_path.clone(),
+    #[test]
+    fn scripted_fail_fails() {
+        let states = run_script("scripted-fail", "fails");
 
-                                name: entry_path.file_stem().unwrap().to_string_lossy().to_string()
-                            }
-                        );
-                    }
-                },
-                Err(err) => {
-                    panic!("Error while getting models: {}", err);
+        assert!(matches!(states.last(), Some(JobState::Failed(Error::FailedToUpscaleImage(..)))));
+    }
+
+    #[test]
+    fn models_are_checked_before_they_run() {
+        let image = test_image("checksums");
+        let folder = image.path.parent().unwrap().to_path_buf();
+        let backend = ScriptedBackend::with_step_delay(Duration::ZERO);
+
+        let mut options = scripted_options(&backend, "scripted");
+        let model = options.model.as_mut().unwrap();
+
+        model.path = folder.join("scripted.onnx");
+        fs::write(&model.path, "model").unwrap();
+
+        let mut hasher = Sha256::new();
+        hasher.update("model");
+        let sha256 = format!("{:x}", hasher.finalize());
+
+        for (manifest_sha256, matches) in [(sha256.as_str(), true), ("0000", false)] {
+            let manifest = format!("[sha256]\n\"scripted.onnx\" = \"{}\"\n", manifest_sha256);
+            fs::write(folder.join("manifest.toml"), manifest).unwrap();
+
+            let verifier = Mutex::new(Verifier::default());
+            let mut last = None;
+
+            run_job(&backend, &image, &options, &verifier, &CancelHandle::default(), |state| last = Some(state.clone()));
+
+            match (matches, last) {
+                (true, Some(JobState::Finished { .. })) => {},
+                (false, Some(JobState::Failed(Error::ModelChecksumMismatch(..)))) => {},
+                (_, state) => panic!("expected the checksum to {}match, got {:?}", if matches { "" } else { "mis" }, state)
+            }
+        }
+    }
+
+    #[test]
+    fn progress_never_decreases() {
+        let states = run_script("scripted-multipass", "progress");
+
+        let progress: Vec<f32> = states.iter()
+            .filter_map(|state| match state {
+                JobState::Running { progress, .. } => Some(*progress),
+                _ => None
+            })
+            .collect();
+
+        assert_eq!(progress.len(), 6);
+        assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", progress);
+        assert!(progress.iter().all(|progress| *progress < 1.0), "{:?}", progress);
+    }
+
+    #[test]
+    fn progress_keeps_moving_through_unexpected_passes() {
+        let mut tracker = ProgressTracker::new(1);
+        let mut progress = Vec::new();
+
+        for percent in [0.0, 50.0, 100.0, 0.0, 50.0, 100.0, 0.0, 100.0] {
+            if let JobState::Running { progress: value, eta, .. } = tracker.update(percent) {
+                progress.push(value);
+
+                if value >= 0.01 {
+                    assert!(eta.is_some());
                 }
             }
         }
+
+        assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", progress);
+        assert!(progress[3..].windows(2).any(|pair| pair[0] < pair[1]), "{:?}", progress);
+        assert!(progress.iter().all(|progress| *progress < 1.0), "{:?}", progress);
+    }
+
+    #[test]
+    fn out_of_memory_retries_with_smaller_tiles() {
+        let backend = Arc::new(ScriptedBackend::with_step_delay(Duration::ZERO));
+        let options = scripted_options(&backend, "scripted-oom");
+        let mut queue = UpscaleQueue::new(1);
+
+        queue.push(test_image("out-of-memory"), options, backend);
+        queue.start(&NotifierAPI::new());
+
+        let jobs = wait_for(&queue, all_done);
+        let job = &jobs[0];
+
+        assert!(matches!(job.state, JobState::Finished { .. }), "{:?}", job.state);
+        assert_eq!(job.history.len(), 3, "{:?}", job.history);
+        assert!(job.history.last().unwrap().contains("tile size of 64"), "{:?}", job.history);
+    }
+
+    #[test]
+    fn cancelling_a_queued_job() {
+        let backend = Arc::new(ScriptedBackend::with_step_delay(Duration::ZERO));
+        let options = scripted_options(&backend, "scripted");
+        let mut queue = UpscaleQueue::new(1);
+
+        let first = queue.push(test_image("cancel-queued-first"), options.clone(), backend.clone());
+        let second = queue.push(test_image("cancel-queued-second"), options, backend);
+
+        queue.cancel(second);
+        queue.start(&NotifierAPI::new());
+
+        let jobs = wait_for(&queue, all_done);
+
+        assert!(matches!(jobs.iter().find(|job| job.id == first).unwrap().state, JobState::Finished { .. }));
+        assert!(matches!(jobs.iter().find(|job| job.id == second).unwrap().state, JobState::Cancelled));
+    }
+
+    #[test]
+    fn cancelling_a_running_job() {
+        let backend = Arc::new(ScriptedBackend::with_step_delay(Duration::from_millis(50)));
+        let options = scripted_options(&backend, "scripted");
+        let mut queue = UpscaleQueue::new(1);
+
+        let id = queue.push(test_image("cancel-running"), options, backend);
+        queue.start(&NotifierAPI::new());
+
+        wait_for(&queue, |jobs| matches!(jobs[0].state, JobState::Running { .. }));
+        queue.cancel(id);
+
+        let jobs = wait_for(&queue, all_done);
+
+        assert!(matches!(jobs[0].state, JobState::Cancelled), "{:?}", jobs[0].state);
     }
 }
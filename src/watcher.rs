@@ -0,0 +1,114 @@
+use std::{path::PathBuf, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::{Cause, Error};
+
+// Copying a model writes its files in bursts, so changes only count once the folders are quiet.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+// Watches the models folders so models can be picked up without restarting.
+pub struct ModelsWatcher {
+    watcher: RecommendedWatcher,
+    watched: Vec<PathBuf>,
+    recursive: bool,
+    changes: Arc<Mutex<Option<Changes>>>
+}
+
+// Everything that changed since the last `take_change`, and when the latest change was.
+struct Changes {
+    at: Instant,
+    paths: Vec<PathBuf>
+}
+
+impl ModelsWatcher {
+    // `on_change` is called from the watcher's own thread, e.g. to wake up the UI.
+    pub fn new(on_change: impl Fn(Duration) + Send + 'static) -> Result<Self, Error> {
+        let changes: Arc<Mutex<Option<Changes>>> = Arc::default();
+        let event_changes = changes.clone();
+
+        let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            let event = match result {
+                Ok(event) => event,
+                Err(error) => {
+                    log::warn!("Error while watching the models folders: {}", error);
+                    return;
+                }
+            };
+
+            // Imports unpack into a folder of their own first, those files aren't models yet.
+            let importing = !event.paths.is_empty() && event.paths.iter()
+                .all(|path| path.components().any(|component| component.as_os_str() == ".aeternum-import"));
+
+            if event.kind.is_access() || importing {
+                return;
+            }
+
+            let mut changes = event_changes.lock().unwrap();
+            let changes = changes.get_or_insert_with(|| Changes { at: Instant::now(), paths: Vec::new() });
+
+            changes.at = Instant::now();
+
+            for path in event.paths {
+                if !changes.paths.contains(&path) {
+                    changes.paths.push(path);
+                }
+            }
+
+            on_change(SETTLE_TIME);
+        });
+
+        match watcher {
+            Ok(watcher) => Ok(
+                Self {
+                    watcher,
+                    watched: Vec::new(),
+                    recursive: false,
+                    changes
+                }
+            ),
+            Err(error) => Err(Error::FailedToWatchModels(Some(Cause::source(error))))
+        }
+    }
+
+    // Watches exactly `folders`, the ones no longer scanned are let go.
+    pub fn watch(&mut self, folders: &[PathBuf], recursive: bool) {
+        let mode = match recursive {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive
+        };
+
+        // Switching between recursive and not means watching every folder over again.
+        let rewatch = recursive != self.recursive;
+        self.recursive = recursive;
+
+        for folder in self.watched.iter().filter(|folder| rewatch || !folders.contains(folder)) {
+            if let Err(error) = self.watcher.unwatch(folder) {
+                log::debug!("Failed to stop watching '{}': {}", folder.display(), error);
+            }
+        }
+
+        self.watched.retain(|folder| !rewatch && folders.contains(folder));
+
+        for folder in folders.iter() {
+            if self.watched.contains(folder) {
+                continue;
+            }
+
+            match self.watcher.watch(folder, mode) {
+                Ok(_) => self.watched.push(folder.clone()),
+                Err(error) => log::warn!("Failed to watch the models folder '{}': {}", folder.display(), error)
+            }
+        }
+    }
+
+    // The paths that changed, once after the folders changed and have since been quiet for a moment.
+    pub fn take_change(&self) -> Option<Vec<PathBuf>> {
+        let mut changes = self.changes.lock().unwrap();
+
+        match changes.as_ref() {
+            Some(settled) if settled.at.elapsed() >= SETTLE_TIME => changes.take().map(|changes| changes.paths),
+            _ => None
+        }
+    }
+}
@@ -52,7 +52,10 @@ impl<'a> AboutWindow<'a> {
     }
 
     pub fn handle_input(&mut self, ctx: &egui::Context) {
-        if ctx.input(|i| i.key_pressed(self.toggle_key)) {
+        // Ignored while typing, e.g. a model's new name.
+        let typing = ctx.memory(|memory| memory.focused().is_some());
+
+        if !typing && ctx.input(|i| i.key_pressed(self.toggle_key)) {
             if self.show == true {
                 self.show = false;
             } else {
@@ -1 +1,4 @@
-pub mod about;
\ No newline at end of file
+pub mod about;
+pub mod models;
+pub mod setup;
+pub mod watch;
\ No newline at end of file
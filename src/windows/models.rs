@@ -0,0 +1,310 @@
+use std::{path::PathBuf, time::Duration};
+
+use eframe::egui::{self, Color32, Key, RichText, Vec2};
+use egui_notify::ToastLevel;
+
+use crate::{config::config::Config, error::Error, files, integrity::Integrity, model_files, notifier::NotifierAPI, upscale::{Model, Upscale}};
+
+// Lists every model that was found and lets the ones in the custom folder be managed
+// without restarting aeternum.
+pub struct ModelsWindow {
+    pub show: bool,
+    toggle_key: Key,
+    // Name of the model being renamed and the name typed in so far.
+    renaming: Option<(String, String)>,
+    // Name of the model waiting for its removal to be confirmed.
+    removing: Option<String>
+}
+
+// Changes to the models asked for in the window, applied once it's drawn.
+enum Action {
+    ImportFiles,
+    ImportArchive,
+    InstallArchive(PathBuf),
+    Rescan,
+    Favourite(String, bool),
+    Rename(Model, String),
+    Remove(Model)
+}
+
+impl ModelsWindow {
+    pub fn new(config: &Config, notifier: &mut NotifierAPI) -> Self {
+        let toggle_key = match Key::from_name(&config.keybinds.model_manager) {
+            Some(key) => key,
+            None => {
+                notifier.toasts.lock().unwrap().toast_and_log(
+                    "The key bind set for 'model_manager' is invalid! Defaulting to `M`.".into(),
+                    ToastLevel::Error
+                );
+
+                Key::M
+            },
+        };
+
+        Self {
+            show: false,
+            toggle_key,
+            renaming: None,
+            removing: None
+        }
+    }
+
+    pub fn handle_input(&mut self, ctx: &egui::Context) {
+        // Ignored while typing, otherwise renaming a model could close the window.
+        let typing = ctx.memory(|memory| memory.focused().is_some());
+
+        if !typing && ctx.input(|i| i.key_pressed(self.toggle_key)) {
+            self.show = !self.show;
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, upscale: &mut Upscale, notifier: &mut NotifierAPI) {
+        if !self.show {
+            return;
+        }
+
+        let default_window_size = Vec2::new(420.0, 450.0);
+        let mut show = self.show;
+        let mut action = None;
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("🗂 Models").size(15.0)
+            )
+        )
+            .open(&mut show)
+            .default_size(default_window_size)
+            .min_width(320.0)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Import models...").on_hover_text("A .bin and .param pair or an .onnx file.").clicked() {
+                        action = Some(Action::ImportFiles);
+                    }
+
+                    if ui.button("Import archive...").on_hover_text("A .zip or .tar.gz with models in it.").clicked() {
+                        action = Some(Action::ImportArchive);
+                    }
+
+                    if ui.button("Rescan").clicked() {
+                        action = Some(Action::Rescan);
+                    }
+                });
+
+                if !upscale.custom_folder_enabled() {
+                    ui.small(
+                        RichText::new("The custom folder is turned off in the config, imported models won't be listed.")
+                            .color(ui.visuals().warn_fg_color)
+                    );
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for model in upscale.models.iter() {
+                        ui.group(|ui| {
+                            ui.set_width(ui.available_width());
+
+                            self.show_model(ui, model, upscale.is_favourite(&model.name), &mut action);
+                        });
+                    }
+                });
+            });
+
+        self.show = show;
+
+        if let Some(action) = action {
+            self.apply(action, upscale, notifier);
+        }
+    }
+
+    // Installs the models in an archive dropped onto the window.
+    pub fn install_archive(&mut self, path: PathBuf, upscale: &mut Upscale, notifier: &mut NotifierAPI) {
+        self.apply(Action::InstallArchive(path), upscale, notifier);
+    }
+
+    fn show_model(&mut self, ui: &mut egui::Ui, model: &Model, favourite: bool, action: &mut Option<Action>) {
+        ui.horizontal(|ui| {
+            let star = match favourite {
+                true => "★",
+                false => "☆"
+            };
+
+            if ui.small_button(star).on_hover_text("Favourites are listed first.").clicked() {
+                *action = Some(Action::Favourite(model.name.clone(), !favourite));
+            }
+
+            ui.label(RichText::new(model.display_name()).strong());
+
+            if model.display_name() != model.name {
+                ui.label(RichText::new(&model.name).monospace().weak());
+            }
+        });
+
+        let location = match model.path.as_os_str().is_empty() {
+            true => format!("{}, built in", model.backend),
+            false => format!("{}, {} in '{}'", model.backend, format_size(model.size), model.folder.display())
+        };
+
+        ui.small(location);
+
+        if let Some(native_scale) = model.native_scale {
+            ui.small(format!("Native scale: {}x", native_scale));
+        }
+
+        match &model.integrity {
+            Integrity::Verified => {
+                ui.small(RichText::new("✔ Matches its manifest").color(Color32::LIGHT_GREEN));
+            },
+            Integrity::Mismatch(files) => {
+                ui.small(RichText::new(format!("✖ Doesn't match its manifest: {}", files.join(", "))).color(ui.visuals().error_fg_color))
+                    .on_hover_text("It can't be upscaled with until it's downloaded again.");
+            },
+            Integrity::Unchecked => {}
+        }
+
+        if let Some(info) = &model.info {
+            info.show(ui);
+        }
+
+        if !model_files::is_custom(model) {
+            return;
+        }
+
+        let mut cancelled = false;
+
+        match (&mut self.renaming, &self.removing) {
+            (Some((name, new_name)), _) if *name == model.name => {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(new_name);
+
+                    if ui.small_button("Save").clicked() {
+                        *action = Some(Action::Rename(model.clone(), new_name.clone()));
+                    }
+
+                    cancelled = ui.small_button("Cancel").clicked();
+                });
+            },
+            (_, Some(name)) if *name == model.name => {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Delete its files?").color(ui.visuals().warn_fg_color));
+
+                    if ui.small_button("Delete").clicked() {
+                        *action = Some(Action::Remove(model.clone()));
+                    }
+
+                    cancelled = ui.small_button("Cancel").clicked();
+                });
+            },
+            _ => {
+                ui.horizontal(|ui| {
+                    if ui.small_button("Rename").clicked() {
+                        self.renaming = Some((model.name.clone(), model.name.clone()));
+                        self.removing = None;
+                    }
+
+                    if ui.small_button("Delete").clicked() {
+                        self.removing = Some(model.name.clone());
+                        self.renaming = None;
+                    }
+                });
+            }
+        }
+
+        if cancelled {
+            self.renaming = None;
+            self.removing = None;
+        }
+    }
+
+    fn apply(&mut self, action: Action, upscale: &mut Upscale, notifier: &mut NotifierAPI) {
+        let result = match action {
+            Action::ImportFiles => files::select_model_files()
+                .and_then(|paths| model_files::import_files(&paths))
+                .map(|imported| Some(imported.summary())),
+            Action::ImportArchive => files::select_archive()
+                .and_then(|path| model_files::import_archive(&path))
+                .map(|imported| Some(imported.summary())),
+            Action::InstallArchive(path) => model_files::import_archive(&path)
+                .map(|imported| Some(imported.summary())),
+            Action::Rescan => Ok(None),
+            Action::Favourite(name, favourite) => {
+                // Starring only changes the order, there's nothing to rescan.
+                if let Err(error) = upscale.set_favourite(&name, favourite) {
+                    notify(notifier, error, ToastLevel::Error);
+                }
+
+                return;
+            },
+            Action::Rename(model, new_name) => {
+                let result = model_files::rename(&model, &new_name);
+
+                if result.is_ok() {
+                    self.renaming = None;
+
+                    if upscale.is_favourite(&model.name) {
+                        let favourites = upscale.set_favourite(&model.name, false)
+                            .and_then(|_| upscale.set_favourite(&new_name, true));
+
+                        if let Err(error) = favourites {
+                            notify(notifier, error, ToastLevel::Warning);
+                        }
+                    }
+                }
+
+                result.map(|_| None)
+            },
+            Action::Remove(model) => {
+                let result = model_files::remove(&model);
+
+                if result.is_ok() {
+                    self.removing = None;
+
+                    if let Err(error) = upscale.set_favourite(&model.name, false) {
+                        notify(notifier, error, ToastLevel::Warning);
+                    }
+                }
+
+                result.map(|_| Some(format!("Removed {}.", model.name)))
+            }
+        };
+
+        let message = match result {
+            Ok(message) => message,
+            Err(Error::NoFileSelected(_)) => return,
+            Err(error) => {
+                notify(notifier, error, ToastLevel::Error);
+                return;
+            }
+        };
+
+        match upscale.rescan() {
+            Ok(_) => {
+                if let Some(message) = message {
+                    notifier.toasts.lock().unwrap()
+                        .toast_and_log(message.into(), ToastLevel::Success)
+                        .duration(Some(Duration::from_secs(10)));
+                }
+            },
+            Err(error) => notify(notifier, error, ToastLevel::Error)
+        }
+
+        for warning in upscale.warnings.clone() {
+            notify(notifier, warning, ToastLevel::Warning);
+        }
+    }
+}
+
+fn notify(notifier: &mut NotifierAPI, error: Error, level: ToastLevel) {
+    notifier.toasts.lock().unwrap()
+        .toast_and_log(error.into(), level)
+        .duration(Some(Duration::from_secs(10)));
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        bytes if bytes >= 1024 * 1024 => format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0)),
+        bytes if bytes >= 1024 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        bytes => format!("{} B", bytes)
+    }
+}
\ No newline at end of file
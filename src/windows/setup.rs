@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use eframe::egui::{self, Color32, RichText, Ui};
+use egui_notify::ToastLevel;
+use toml_edit::{value, Array};
+
+use crate::{backends::ncnn::NcnnBackend, config::config::Config, error::Error, files, notifier::NotifierAPI, upscale::Upscale};
+
+// Shown instead of the main screen when upscalers or models are missing, so they can be
+// picked by hand and saved to the config rather than aeternum refusing to start.
+pub struct SetupScreen {
+    config: Config,
+    problems: Vec<Error>,
+
+    binary_status: Option<Result<String, Error>>,
+    // Set up from the picked paths once they're changed, until then the app's own `Upscale`
+    // (set up from the initial config) is the one that's checked and kept.
+    pending: Option<Upscale>,
+    pending_result: Result<(), Error>
+}
+
+impl SetupScreen {
+    // `init_result` is what setting up the app's `Upscale` from `config` returned.
+    pub fn new(config: Config, problems: Vec<Error>, init_result: Result<(), Error>) -> Self {
+        let mut setup = Self {
+            config,
+            problems,
+
+            binary_status: None,
+            pending: None,
+            pending_result: init_result
+        };
+
+        setup.check_binary();
+        setup
+    }
+
+    fn check_binary(&mut self) {
+        self.binary_status = self.config.upscale.binary_path.as_ref().map(|binary_path| {
+            let binary_path = files::expand_path(binary_path);
+            let profile = NcnnBackend::profile_for_path(&binary_path);
+
+            NcnnBackend::with_cli_path(profile, binary_path).map(|backend| {
+                match backend.models_folder() {
+                    Some(folder) => format!("Using it as {}, with the models in '{}'.", backend.profile_name(), folder.display()),
+                    None => format!("Using it as {}. No models folder was found for it, add one below.", backend.profile_name())
+                }
+            })
+        });
+    }
+
+    // Checks the picked binary and folders again, called whenever one of them changes.
+    fn validate(&mut self) {
+        self.check_binary();
+
+        let (pending, pending_result) = Upscale::from_config(&self.config);
+
+        self.pending = Some(pending);
+        self.pending_result = pending_result;
+    }
+
+    fn folder_status(&self, upscale: &Upscale) -> Vec<Result<usize, Error>> {
+        self.config.upscale.models_folders.iter().map(|folder| {
+            let folder = &files::expand_path(folder);
+
+            if !folder.is_dir() {
+                return Err(Error::ModelsFolderNotFound(None, folder.clone()));
+            }
+
+            let count = upscale.models.iter()
+                .filter(|model| model.path.starts_with(folder))
+                .count();
+
+            match count {
+                0 => Err(Error::NoModels(None, folder.clone())),
+                count => Ok(count)
+            }
+        }).collect()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let binary_path = self.config.upscale.binary_path.clone();
+        let models_folders: Array = self.config.upscale.models_folders.iter()
+            .map(|folder| folder.to_string_lossy().to_string())
+            .collect();
+
+        Config::update(|document| {
+            document["misc"]["setup_complete"] = value(true);
+            document["upscale"]["models_folders"] = value(models_folders);
+
+            match binary_path {
+                Some(binary_path) => document["upscale"]["binary_path"] = value(binary_path.to_string_lossy().to_string()),
+                None => {
+                    if let Some(upscale) = document["upscale"].as_table_like_mut() {
+                        upscale.remove("binary_path");
+                    }
+                }
+            }
+        })
+    }
+
+    // Returns true once the user is done, `upscale` is swapped for the one set up from the
+    // picked paths if they were saved.
+    pub fn show(&mut self, ui: &mut Ui, notifier: &mut NotifierAPI, upscale: &mut Upscale) -> bool {
+        let mut finished = None;
+        let folder_status = self.folder_status(self.pending.as_ref().unwrap_or(upscale));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Set up Aeternum");
+                ui.label("Some of what aeternum upscales with couldn't be found. Point it at them below.");
+            });
+
+            ui.add_space(15.0);
+
+            if !self.problems.is_empty() {
+                ui.label(RichText::new("What's missing").strong());
+
+                for problem in self.problems.iter() {
+                    ui.label(format!("• {}", problem)).on_hover_text(problem.detail());
+                }
+
+                ui.add_space(15.0);
+            }
+
+            ui.label(RichText::new("Upscaler binary").strong());
+            ui.small("upscayl-bin, realesrgan-ncnn-vulkan, waifu2x-ncnn-vulkan or realcugan-ncnn-vulkan.");
+
+            let mut changed = false;
+
+            ui.horizontal(|ui| {
+                match &self.config.upscale.binary_path {
+                    Some(binary_path) => ui.monospace(binary_path.to_string_lossy()),
+                    None => ui.label("None picked, only the ones in your PATH are used.")
+                };
+
+                if ui.button("Browse...").clicked() {
+                    match files::select_binary() {
+                        Ok(binary_path) => {
+                            self.config.upscale.binary_path = Some(binary_path);
+                            changed = true;
+                        },
+                        Err(Error::NoFileSelected(_)) => {},
+                        Err(error) => {
+                            notifier.toasts.lock().unwrap()
+                                .toast_and_log(error.into(), ToastLevel::Error)
+                                .duration(Some(Duration::from_secs(5)));
+                        }
+                    }
+                }
+
+                if self.config.upscale.binary_path.is_some() && ui.button("Clear").clicked() {
+                    self.config.upscale.binary_path = None;
+                    changed = true;
+                }
+            });
+
+            match &self.binary_status {
+                Some(Ok(message)) => status_label(ui, Ok(message.clone())),
+                Some(Err(error)) => status_label(ui, Err(error)),
+                None => {}
+            }
+
+            ui.add_space(15.0);
+
+            ui.label(RichText::new("Model folders").strong());
+            ui.small("Folders with .bin and .param pairs, models-* folders or .onnx files.");
+
+            let mut removed = None;
+
+            for (index, folder) in self.config.upscale.models_folders.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(folder.to_string_lossy());
+
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+
+                match folder_status.get(index) {
+                    Some(Ok(count)) => status_label(ui, Ok(format!("{} model(s) found.", count))),
+                    Some(Err(error)) => status_label(ui, Err(error)),
+                    None => {}
+                }
+            }
+
+            if let Some(index) = removed {
+                self.config.upscale.models_folders.remove(index);
+                changed = true;
+            }
+
+            if ui.button("Add folder...").clicked() {
+                match files::save_folder() {
+                    Ok(folder) => {
+                        if !self.config.upscale.models_folders.contains(&folder) {
+                            self.config.upscale.models_folders.push(folder);
+                            changed = true;
+                        }
+                    },
+                    Err(Error::NoFileSelected(_)) => {},
+                    Err(error) => {
+                        notifier.toasts.lock().unwrap()
+                            .toast_and_log(error.into(), ToastLevel::Error)
+                            .duration(Some(Duration::from_secs(5)));
+                    }
+                }
+            }
+
+            if changed {
+                self.validate();
+            }
+
+            ui.add_space(15.0);
+
+            match &self.pending_result {
+                Ok(_) => ui.label(
+                    format!("{} model(s) will be available.", self.pending.as_ref().unwrap_or(upscale).models.len())
+                ),
+                Err(error) => ui.label(RichText::new(error.to_string()).color(ui.visuals().error_fg_color))
+            };
+
+            ui.add_space(10.0);
+
+            let binary_ok = !matches!(self.binary_status, Some(Err(_)));
+            let ready = binary_ok && self.pending_result.is_ok();
+
+            ui.horizontal(|ui| {
+                let save_button = ui.add_enabled(ready, egui::Button::new("Save and continue"))
+                    .on_disabled_hover_text("Fix the problems above first.");
+
+                if save_button.clicked() {
+                    match self.save() {
+                        Ok(_) => finished = Some(true),
+                        Err(error) => {
+                            notifier.toasts.lock().unwrap()
+                                .toast_and_log(error.into(), ToastLevel::Error)
+                                .duration(Some(Duration::from_secs(10)));
+                        }
+                    }
+                }
+
+                let skip_button = ui.add_enabled(
+                    self.pending_result.is_ok(),
+                    egui::Button::new("Skip for now")
+                ).on_hover_text("Continue with what was found, this screen won't open again on its own.");
+
+                if skip_button.clicked() {
+                    // Only remember that the setup was seen, the picked paths are thrown away.
+                    let result = Config::update(|document| {
+                        document["misc"]["setup_complete"] = value(true);
+                    });
+
+                    if let Err(error) = result {
+                        notifier.toasts.lock().unwrap()
+                            .toast_and_log(error.into(), ToastLevel::Warning)
+                            .duration(Some(Duration::from_secs(10)));
+                    }
+
+                    finished = Some(false);
+                }
+            });
+        });
+
+        match finished {
+            Some(true) => {
+                if let Some(pending) = self.pending.take() {
+                    *upscale = pending;
+                }
+
+                true
+            },
+            // The app's `Upscale` was set up from the initial config, so it's kept as is.
+            Some(false) => true,
+            None => false
+        }
+    }
+}
+
+fn status_label(ui: &mut Ui, status: Result<String, &Error>) {
+    match status {
+        Ok(message) => ui.label(RichText::new(format!("✔ {}", message)).color(Color32::LIGHT_GREEN)),
+        Err(error) => ui.label(RichText::new(format!("✖ {}", error)).color(ui.visuals().error_fg_color))
+            .on_hover_text(error.detail())
+    };
+}
\ No newline at end of file
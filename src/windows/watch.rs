@@ -0,0 +1,364 @@
+use std::{path::{Path, PathBuf}, time::Duration};
+
+use eframe::egui::{self, RichText, Slider, Vec2};
+use egui_notify::ToastLevel;
+use toml_edit::{value, Array};
+
+use crate::{config::{config::Config, structs::watch::Watch}, error::Error, files, hot_folder::{self, HotFolder}, image_folder::FolderFilter, notifier::NotifierAPI, upscale::{JobState, Upscale}};
+
+// Sets up the hot folder and its preset, and upscales what shows up in it while watching.
+pub struct WatchWindow {
+    pub show: bool,
+    settings: Watch,
+    filter: FolderFilter,
+    hot_folder: Option<HotFolder>,
+    // Queued jobs of images from the hot folder.
+    jobs: Vec<(usize, PathBuf)>,
+    upscaled: usize
+}
+
+impl WatchWindow {
+    pub fn new(config: &Config) -> Self {
+        // Invalid patterns are already reported when the app starts.
+        let filter = FolderFilter::new(&config.upscale.folder_include, &config.upscale.folder_exclude)
+            .unwrap_or_default();
+
+        Self {
+            show: false,
+            settings: config.watch.clone(),
+            filter,
+            hot_folder: None,
+            jobs: Vec::new(),
+            upscaled: 0
+        }
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.hot_folder.is_some()
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, upscale: &mut Upscale, notifier: &mut NotifierAPI) {
+        self.process(upscale, notifier);
+
+        if self.is_watching() {
+            ctx.request_repaint_after(hot_folder::POLL_INTERVAL);
+        }
+
+        if !self.show {
+            return;
+        }
+
+        let default_window_size = Vec2::new(380.0, 320.0);
+        let mut show = self.show;
+        let mut changed = false;
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("👁 Watch folder").size(15.0)
+            )
+        )
+            .open(&mut show)
+            .default_size(default_window_size)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                ui.label("New images in the watched folder are upscaled with the preset once they stop changing.");
+
+                ui.add_space(5.0);
+
+                // Changes only apply when watching starts again.
+                ui.add_enabled_ui(!self.is_watching(), |ui| {
+                    egui::Grid::new("watch_grid")
+                        .num_columns(2)
+                        .spacing([10.0, 8.0])
+                        .show(ui, |ui| {
+                            ui.label("Watched folder");
+                            changed |= folder_picker(ui, &mut self.settings.input, "Not set", false, notifier);
+                            ui.end_row();
+
+                            ui.label("Output folder");
+                            changed |= folder_picker(ui, &mut self.settings.output, "\"upscaled\" inside it", true, notifier);
+                            ui.end_row();
+
+                            ui.label("Wait until unchanged");
+                            changed |= ui.add(Slider::new(&mut self.settings.stable_seconds, 0..=30).suffix("s")).changed();
+                            ui.end_row();
+
+                            ui.label("Preset");
+                            ui.horizontal(|ui| {
+                                let preset = match &self.settings.model {
+                                    Some(model) => format!(
+                                        "{}, {}, {}",
+                                        model,
+                                        self.settings.scale.map_or("native scale".to_string(), |scale| format!("x{}", scale)),
+                                        self.settings.format.to_uppercase()
+                                    ),
+                                    None => "None saved".to_string()
+                                };
+
+                                ui.label(preset);
+
+                                let save_button = ui.add_enabled(upscale.options.model.is_some(), egui::Button::new("Save preset"))
+                                    .on_hover_text("Uses the model, scale, format, compression, denoise, sync gap and advanced options picked in the options.")
+                                    .on_disabled_hover_text("Select a model first.");
+
+                                if save_button.clicked() {
+                                    let options = &upscale.options;
+
+                                    self.settings.model = options.model.as_ref().map(|model| model.name.clone());
+                                    self.settings.scale = Some(options.scale);
+                                    self.settings.format = options.output_ext.to_string().to_lowercase();
+                                    self.settings.compression = options.compression;
+                                    self.settings.noise = options.noise;
+                                    self.settings.syncgap = Some(options.syncgap);
+                                    self.settings.tile_size = Some(options.tuning.tile_size);
+                                    self.settings.threads = Some(options.tuning.threads);
+                                    self.settings.tta = Some(options.tuning.tta);
+                                    self.settings.gpu_id = options.tuning.gpu_id;
+
+                                    changed = true;
+                                }
+                            });
+                            ui.end_row();
+                        });
+
+                    changed |= ui.checkbox(
+                        &mut self.settings.move_processed,
+                        "Move upscaled originals into a \"processed\" folder"
+                    ).on_hover_text("Otherwise they're left where they are. Either way they're never upscaled twice.").changed();
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    match &self.hot_folder {
+                        Some(hot_folder) => {
+                            if ui.button("Stop watching").clicked() {
+                                self.stop();
+                            } else {
+                                ui.label(
+                                    RichText::new(format!("Watching, {} upscaled, {} waiting", self.upscaled, hot_folder.waiting()))
+                                        .color(ui.visuals().selection.bg_fill)
+                                );
+                            }
+                        },
+                        None => {
+                            if ui.button("Start watching").clicked() {
+                                self.start(upscale, notifier);
+                            }
+                        }
+                    }
+                });
+            });
+
+        self.show = show;
+
+        if changed {
+            if let Err(error) = self.save() {
+                notify(notifier, error, ToastLevel::Error);
+            }
+        }
+    }
+
+    fn start(&mut self, upscale: &Upscale, notifier: &mut NotifierAPI) {
+        let input = match &self.settings.input {
+            Some(input) => files::expand_path(input),
+            None => {
+                notify(notifier, Error::NoFileSelected(None), ToastLevel::Error);
+                return;
+            }
+        };
+
+        let hot_folder = hot_folder::preset_options(&self.settings, upscale).and_then(|options| {
+            HotFolder::new(
+                input,
+                options,
+                self.settings.move_processed,
+                Duration::from_secs(self.settings.stable_seconds),
+                self.filter.clone()
+            )
+        });
+
+        match hot_folder {
+            Ok(hot_folder) => {
+                log::info!("Watching '{}' for new images.", hot_folder.input.display());
+
+                self.hot_folder = Some(hot_folder);
+                self.upscaled = 0;
+            },
+            Err(error) => notify(notifier, error, ToastLevel::Error)
+        }
+    }
+
+    // Queued images are still upscaled, they just aren't noted as processed.
+    fn stop(&mut self) {
+        self.hot_folder = None;
+        self.jobs.clear();
+    }
+
+    // Queues new images from the hot folder and notes the finished ones as processed.
+    fn process(&mut self, upscale: &mut Upscale, notifier: &mut NotifierAPI) {
+        let hot_folder = match self.hot_folder.as_mut() {
+            Some(hot_folder) => hot_folder,
+            None => return
+        };
+
+        let queued = upscale.queue.jobs();
+        let mut failed = None;
+
+        self.jobs.retain(|(id, path)| {
+            let state = match queued.iter().find(|job| job.id == *id) {
+                Some(job) => &job.state,
+                // Cleared from the queue before it was seen finishing, it's picked up again.
+                None => {
+                    hot_folder.forget(path);
+                    return false;
+                }
+            };
+
+            let output = match state {
+                JobState::Finished { output, .. } => Some(output.clone()),
+                // Failed or cancelled, it's left alone until it changes or watching starts again.
+                state if state.is_done() => None,
+                _ => return true
+            };
+
+            if output.is_some() {
+                self.upscaled += 1;
+            }
+
+            if let Err(error) = hot_folder.finish(path, output) {
+                failed = Some(error);
+            }
+
+            false
+        });
+
+        // Without the record every image would be upscaled again and again.
+        if let Some(error) = failed {
+            notify(notifier, error, ToastLevel::Error);
+            self.stop();
+            return;
+        }
+
+        let scan = hot_folder.poll();
+
+        for warning in scan.warnings {
+            notify(notifier, warning, ToastLevel::Warning);
+        }
+
+        if scan.images.is_empty() {
+            return;
+        }
+
+        let paths: Vec<PathBuf> = scan.images.iter().map(|image| image.path.clone()).collect();
+
+        let jobs = scan.images.into_iter()
+            .map(|image| (image, hot_folder.options.clone()))
+            .collect();
+
+        // The preset's model went away or fails its checksum, nothing can be upscaled.
+        let ids = match upscale.upscale_each(jobs, notifier) {
+            Ok(ids) => ids,
+            Err(error) => {
+                notify(notifier, error, ToastLevel::Error);
+                self.stop();
+                return;
+            }
+        };
+
+        self.jobs.extend(ids.into_iter().zip(paths));
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let settings = &self.settings;
+
+        Config::update(|document| {
+            let watch = &mut document["watch"];
+            let path = |path: &Option<PathBuf>| path.as_deref().map(|path| value(path.to_string_lossy().as_ref()));
+            let number = |number: Option<i64>| number.map(value);
+
+            // Unset ones are removed, e.g. a GPU id left for the tool to pick.
+            let optional = [
+                ("input", path(&settings.input)),
+                ("output", path(&settings.output)),
+                ("syncgap", number(settings.syncgap.map(i64::from))),
+                ("tile_size", number(settings.tile_size.map(i64::from))),
+                ("threads", settings.threads.map(|threads| value(threads.iter().map(|count| *count as i64).collect::<Array>()))),
+                ("tta", settings.tta.map(value)),
+                ("gpu_id", number(settings.gpu_id.map(i64::from)))
+            ];
+
+            for (key, item) in optional {
+                match item {
+                    Some(item) => watch[key] = item,
+                    None => {
+                        watch.as_table_like_mut().map(|table| table.remove(key));
+                    }
+                }
+            }
+
+            watch["move_processed"] = value(settings.move_processed);
+            watch["stable_seconds"] = value(settings.stable_seconds as i64);
+
+            if let Some(model) = &settings.model {
+                watch["model"] = value(model);
+            }
+
+            if let Some(scale) = settings.scale {
+                watch["scale"] = value(scale as i64);
+            }
+
+            watch["format"] = value(&settings.format);
+            watch["compression"] = value(settings.compression as i64);
+            watch["noise"] = value(settings.noise as i64);
+        })
+    }
+}
+
+// A folder from the settings with buttons to pick it, and to unset it if `clearable`.
+// Returns true if it was changed.
+fn folder_picker(
+    ui: &mut egui::Ui,
+    folder: &mut Option<PathBuf>,
+    unset_text: &str,
+    clearable: bool,
+    notifier: &mut NotifierAPI
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let text = folder.as_deref()
+            .and_then(Path::file_name)
+            .map_or(unset_text.to_string(), |name| name.to_string_lossy().to_string());
+
+        let label = ui.label(text);
+
+        if let Some(folder) = folder.as_ref() {
+            label.on_hover_text(folder.to_string_lossy());
+        }
+
+        if ui.small_button("Pick...").clicked() {
+            match files::save_folder() {
+                Ok(picked) => {
+                    *folder = Some(picked);
+                    changed = true;
+                },
+                Err(Error::NoFileSelected(_)) => {},
+                Err(error) => notify(notifier, error, ToastLevel::Error)
+            }
+        }
+
+        if clearable && folder.is_some() && ui.small_button("✖").clicked() {
+            *folder = None;
+            changed = true;
+        }
+    });
+
+    changed
+}
+
+fn notify(notifier: &mut NotifierAPI, error: Error, level: ToastLevel) {
+    notifier.toasts.lock().unwrap()
+        .toast_and_log(error.into(), level)
+        .duration(Some(Duration::from_secs(10)));
+}
\ No newline at end of file